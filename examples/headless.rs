@@ -0,0 +1,126 @@
+use ash::extensions::nv;
+use ash::version::DeviceV1_0;
+use ash_nv_rt::scene::IDENTITY_TRANSFORM;
+use ash_nv_rt::{
+    load_gltf, load_obj, Base, Mesh, MeshInstance, Model, RayTracingApp, Result, Scene,
+    ShaderConfig, ShaderLanguage, Vertex,
+};
+use std::path::Path;
+use std::rc::Rc;
+
+fn main() -> Result<()> {
+    let base = Rc::new(Base::new_headless(1024, 768)?);
+    let props_rt = unsafe { nv::RayTracing::get_properties(&base.instance, base.pdevice) };
+    let ray_tracing = Rc::new(nv::RayTracing::new(&base.instance, &base.device));
+
+    let (flags, args): (Vec<String>, Vec<String>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let has_flag = |name: &str| flags.iter().any(|flag| flag == name);
+    let shader_config = ShaderConfig {
+        language: if has_flag("--glsl") {
+            ShaderLanguage::Glsl
+        } else {
+            ShaderLanguage::Hlsl
+        },
+        library: has_flag("--lib"),
+        bindless: !has_flag("--no-bindless"),
+        path_tracing: has_flag("--path-trace"),
+        shadows: has_flag("--shadows"),
+    };
+    let mut app = RayTracingApp::new(base.clone(), ray_tracing, props_rt, shader_config);
+    app.set_pipeline_cache_path(&std::env::temp_dir().join("ash-nv-rt-pipeline.cache"));
+    app.set_compaction(has_flag("--compact"));
+
+    let mut args = args.into_iter();
+    let output = args.next().unwrap_or_else(|| "headless.png".to_owned());
+    let mut scene = match args.next() {
+        Some(scene_path) => {
+            let scene_path = Path::new(&scene_path);
+            match scene_path
+                .extension()
+                .and_then(|extension| extension.to_str())
+            {
+                Some("gltf") | Some("glb") => load_gltf(scene_path)?,
+                _ => Scene::from_meshes("obj", load_obj(scene_path)?),
+            }
+        }
+        None => Scene::triangles(),
+    };
+    if has_flag("--sphere") {
+        scene.materials.push([1.0, 1.0, 0.0]);
+        let material = scene.materials.len() as u32 - 1;
+        scene.add_sphere([0.0, 0.9, 0.5], 0.5, material);
+    }
+    if has_flag("--shadows") {
+        // A white wall behind the scene for the shadows to fall on
+        scene.materials.push([1.0, 1.0, 1.0]);
+        let vertices = [[-4.0, -3.0], [4.0, -3.0], [4.0, 3.0], [-4.0, 3.0]]
+            .iter()
+            .map(|&[x, y]| Vertex { pos: [x, y, 1.0] })
+            .collect();
+        let wall = Mesh::new(
+            "wall".to_owned(),
+            vertices,
+            Vec::new(),
+            Vec::new(),
+            vec![0, 1, 2, 0, 2, 3],
+        );
+        scene.models.push(Model {
+            name: "wall".to_owned(),
+            meshes: vec![wall],
+            aabbs: Vec::new(),
+            deformable: false,
+        });
+        scene.instances.push(MeshInstance {
+            model: scene.models.len() - 1,
+            transform: IDENTITY_TRANSFORM,
+            custom_id: scene.materials.len() as u32 - 1,
+        });
+    }
+    app.set_scene(scene);
+
+    if has_flag("--callable") {
+        app.set_closest_hit_shader(Path::new(
+            "shaders/compiled/triangle.hlsl_callable_rchit.spv",
+        ));
+        app.add_callable_shader(Path::new("shaders/compiled/shade.hlsl_rcall.spv"));
+    }
+
+    app.initialize()?;
+
+    if let Some(stats) = app.compaction_stats() {
+        println!(
+            "Compacted bottom-level acceleration structures from {} to {} bytes",
+            stats.original_size, stats.compacted_size
+        );
+    }
+
+    let memory = base.allocator.stats();
+    println!(
+        "Sub-allocated {} resources from {} memory blocks, {} of {} bytes in use",
+        memory.allocation_count, memory.block_count, memory.used_bytes, memory.reserved_bytes
+    );
+
+    // Path traced frames accumulate; every other mode renders the same one
+    let samples: u32 = flags
+        .iter()
+        .find_map(|flag| flag.strip_prefix("--samples="))
+        .and_then(|samples| samples.parse().ok())
+        .unwrap_or(1);
+    for _ in 1..samples {
+        app.render_offscreen()?;
+    }
+    let image = app.capture_offscreen()?;
+    image.save(Path::new(&output))?;
+
+    println!(
+        "Rendered {}x{} frame offscreen to {}",
+        image.width, image.height, output
+    );
+
+    unsafe {
+        base.device.device_wait_idle()?;
+    }
+    app.release()
+}
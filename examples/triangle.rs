@@ -0,0 +1,188 @@
+use ash::extensions::nv;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash_nv_rt::{
+    Base, Camera, CameraController, FrameManager, RayTracingApp, Result, Scene, ShaderConfig,
+    ShaderLanguage, ShaderWatcher, FRAMES_IN_FLIGHT,
+};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+use winit::{Event, WindowEvent};
+
+fn main() -> Result<()> {
+    unsafe {
+        let base = Rc::new(Base::new(1024, 768)?);
+        let props_rt = nv::RayTracing::get_properties(&base.instance, base.pdevice);
+        let ray_tracing = Rc::new(nv::RayTracing::new(&base.instance, &base.device));
+        let has_flag = |name: &str| std::env::args().any(|arg| arg == name);
+        let shader_config = ShaderConfig {
+            language: if has_flag("--glsl") {
+                ShaderLanguage::Glsl
+            } else {
+                ShaderLanguage::Hlsl
+            },
+            library: has_flag("--lib"),
+            bindless: !has_flag("--no-bindless"),
+            path_tracing: has_flag("--path-trace"),
+            shadows: has_flag("--shadows"),
+        };
+        let mut frames = FrameManager::new(FRAMES_IN_FLIGHT, base.clone())?;
+        let mut app = RayTracingApp::new(base.clone(), ray_tracing, props_rt, shader_config);
+        app.set_frames_in_flight(frames.frame_count());
+        app.set_pipeline_cache_path(&std::env::temp_dir().join("ash-nv-rt-pipeline.cache"));
+        if has_flag("--deform") {
+            let mut scene = Scene::triangles();
+            for model in &mut scene.models {
+                model.deformable = true;
+            }
+            app.set_scene(scene);
+            app.set_deformer(Path::new("shaders/compiled/wave.glsl_comp.spv"));
+        }
+        app.initialize()?;
+
+        println!("NV Ray Tracing Properties:");
+        println!(
+            " shader_group_handle_size: {}",
+            props_rt.shader_group_handle_size
+        );
+        println!(" max_recursion_depth: {}", props_rt.max_recursion_depth);
+        println!(
+            " max_shader_group_stride: {}",
+            props_rt.max_shader_group_stride
+        );
+        println!(
+            " shader_group_base_alignment: {}",
+            props_rt.shader_group_base_alignment
+        );
+        println!(" max_geometry_count: {}", props_rt.max_geometry_count);
+        println!(" max_instance_count: {}", props_rt.max_instance_count);
+        println!(" max_triangle_count: {}", props_rt.max_triangle_count);
+        println!(
+            " max_descriptor_set_acceleration_structures: {}",
+            props_rt.max_descriptor_set_acceleration_structures
+        );
+
+        let mut watcher = if has_flag("--watch") {
+            Some(ShaderWatcher::new(Path::new("shaders"))?)
+        } else {
+            None
+        };
+
+        let mut camera = Camera::default();
+        let mut controller = CameraController::new();
+
+        let presentation = base.presentation();
+        let start = Instant::now();
+        let mut last_frame = start;
+        let mut swapchain_out_of_date = false;
+        base.render_loop(|event| {
+            if let Event::WindowEvent {
+                event: WindowEvent::Resized(_),
+                ..
+            } = event
+            {
+                swapchain_out_of_date = true;
+            }
+            if swapchain_out_of_date {
+                let extent = base
+                    .recreate_swapchain()
+                    .expect("Failed to recreate swapchain");
+                if extent.width == 0 || extent.height == 0 {
+                    // Minimized; nothing to draw into until restored
+                    return;
+                }
+                app.resize(extent).expect("Failed to resize");
+                swapchain_out_of_date = false;
+            }
+
+            // Fly with WASD, Q and E; look around with the right mouse button
+            controller.handle_event(event);
+            let now = Instant::now();
+            controller.update(&mut camera, (now - last_frame).as_secs_f32());
+            last_frame = now;
+            app.set_camera(&camera);
+
+            if let Some(watcher) = &mut watcher {
+                let changed = watcher.changed().expect("Failed to watch shaders");
+                if !changed.is_empty() {
+                    println!("Reloading shaders after changes to {:?}", changed);
+                    if let Err(err) = app.reload_shaders() {
+                        eprintln!("Keeping the previous pipeline: {}", err);
+                    }
+                }
+            }
+
+            // Spin the middle triangle about its own origin, unless path
+            // tracing, where that would restart the accumulation every frame.
+            let angle = start.elapsed().as_secs_f32();
+            if !shader_config.path_tracing {
+                let (sin, cos) = angle.sin_cos();
+                app.update_instances(|instances| {
+                    let transform = &mut instances[1].transform;
+                    transform[0] = cos;
+                    transform[1] = -sin;
+                    transform[4] = sin;
+                    transform[5] = cos;
+                });
+            }
+            app.set_animation_time(angle);
+
+            // Blocks only while the GPU is still on this slot's last frame
+            let frame = frames.next_frame().expect("Failed to wait for frame");
+
+            let present_index = match presentation.swapchain_loader.acquire_next_image(
+                presentation.swapchain.get(),
+                u64::MAX,
+                frame.image_available,
+                vk::Fence::null(),
+            ) {
+                // A suboptimal image can still be presented; replace the
+                // swapchain before the next frame
+                Ok((present_index, suboptimal)) => {
+                    swapchain_out_of_date |= suboptimal;
+                    present_index
+                }
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                    swapchain_out_of_date = true;
+                    return;
+                }
+                Err(err) => panic!("Failed to acquire swapchain image: {}", err),
+            };
+
+            app.begin_frame(frame.index)
+                .expect("Failed to update frame resources");
+            frames
+                .submit(
+                    &frame,
+                    vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                    |command_buffer| {
+                        let present_image =
+                            presentation.present_images.borrow()[present_index as usize];
+                        app.record_command_buffer(present_image, command_buffer);
+                    },
+                )
+                .expect("Failed to submit frame");
+
+            let wait_semaphores = [frame.render_finished];
+            let swapchains = [presentation.swapchain.get()];
+            let image_indices = [present_index];
+            let present_info = vk::PresentInfoKHR::builder()
+                .wait_semaphores(&wait_semaphores)
+                .swapchains(&swapchains)
+                .image_indices(&image_indices);
+
+            match presentation
+                .swapchain_loader
+                .queue_present(base.present_queue, &present_info)
+            {
+                Ok(suboptimal) => swapchain_out_of_date |= suboptimal,
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => swapchain_out_of_date = true,
+                Err(err) => panic!("Failed to present swapchain image: {}", err),
+            }
+        });
+
+        base.device.device_wait_idle()?;
+        app.release()
+    }
+}
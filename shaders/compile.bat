@@ -1,7 +1,35 @@
+REM "** Compiling pathtrace.rchit.hlsl"
+dxc.exe -T lib_6_3 pathtrace.rchit.hlsl -Fo compiled/pathtrace.hlsl_rchit.dxil
+dxc.exe -T lib_6_3 -spirv pathtrace.rchit.hlsl -Fo compiled/pathtrace.hlsl_rchit.spv
+
+REM "** Compiling pathtrace.rgen.hlsl"
+dxc.exe -T lib_6_3 pathtrace.rgen.hlsl -Fo compiled/pathtrace.hlsl_rgen.dxil
+dxc.exe -T lib_6_3 -spirv pathtrace.rgen.hlsl -Fo compiled/pathtrace.hlsl_rgen.spv
+
+REM "** Compiling pathtrace.rmiss.hlsl"
+dxc.exe -T lib_6_3 pathtrace.rmiss.hlsl -Fo compiled/pathtrace.hlsl_rmiss.dxil
+dxc.exe -T lib_6_3 -spirv pathtrace.rmiss.hlsl -Fo compiled/pathtrace.hlsl_rmiss.spv
+
+REM "** Compiling shade.rcall.hlsl"
+dxc.exe -T lib_6_3 shade.rcall.hlsl -Fo compiled/shade.hlsl_rcall.dxil
+dxc.exe -T lib_6_3 -spirv shade.rcall.hlsl -Fo compiled/shade.hlsl_rcall.spv
+
+REM "** Compiling shadow.rmiss.hlsl"
+dxc.exe -T lib_6_3 shadow.rmiss.hlsl -Fo compiled/shadow.hlsl_rmiss.dxil
+dxc.exe -T lib_6_3 -spirv shadow.rmiss.hlsl -Fo compiled/shadow.hlsl_rmiss.spv
+
+REM "** Compiling sphere.rint.hlsl"
+dxc.exe -T lib_6_3 sphere.rint.hlsl -Fo compiled/sphere.hlsl_rint.dxil
+dxc.exe -T lib_6_3 -spirv sphere.rint.hlsl -Fo compiled/sphere.hlsl_rint.spv
+
 REM "** Compiling triangle.bindless.rchit.hlsl"
 dxc.exe -T lib_6_3 triangle.bindless.rchit.hlsl -Fo compiled/triangle.hlsl_bindless_rchit.dxil
 dxc.exe -T lib_6_3 -spirv triangle.bindless.rchit.hlsl -Fo compiled/triangle.hlsl_bindless_rchit.spv
 
+REM "** Compiling triangle.callable.rchit.hlsl"
+dxc.exe -T lib_6_3 triangle.callable.rchit.hlsl -Fo compiled/triangle.hlsl_callable_rchit.dxil
+dxc.exe -T lib_6_3 -spirv triangle.callable.rchit.hlsl -Fo compiled/triangle.hlsl_callable_rchit.spv
+
 REM "** Compiling triangle.lib.hlsl"
 dxc.exe -T lib_6_3 triangle.lib.hlsl -Fo compiled/triangle.hlsl_lib.dxil
 dxc.exe -T lib_6_3 -spirv triangle.lib.hlsl -Fo compiled/triangle.hlsl_lib.spv
@@ -18,11 +46,23 @@ REM "** Compiling triangle.rmiss.hlsl"
 dxc.exe -T lib_6_3 triangle.rmiss.hlsl -Fo compiled/triangle.hlsl_rmiss.dxil
 dxc.exe -T lib_6_3 -spirv triangle.rmiss.hlsl -Fo compiled/triangle.hlsl_rmiss.spv
 
+REM "** Compiling triangle.shadow.rchit.hlsl"
+dxc.exe -T lib_6_3 triangle.shadow.rchit.hlsl -Fo compiled/triangle.hlsl_shadow_rchit.dxil
+dxc.exe -T lib_6_3 -spirv triangle.shadow.rchit.hlsl -Fo compiled/triangle.hlsl_shadow_rchit.spv
+
 REM "** Disassembling SPIR-V"
+spirv-dis.exe -o compiled/pathtrace.hlsl_rchit.txt compiled/pathtrace.hlsl_rchit.spv
+spirv-dis.exe -o compiled/pathtrace.hlsl_rgen.txt compiled/pathtrace.hlsl_rgen.spv
+spirv-dis.exe -o compiled/pathtrace.hlsl_rmiss.txt compiled/pathtrace.hlsl_rmiss.spv
+spirv-dis.exe -o compiled/shade.hlsl_rcall.txt compiled/shade.hlsl_rcall.spv
+spirv-dis.exe -o compiled/shadow.hlsl_rmiss.txt compiled/shadow.hlsl_rmiss.spv
+spirv-dis.exe -o compiled/sphere.hlsl_rint.txt compiled/sphere.hlsl_rint.spv
 spirv-dis.exe -o compiled/triangle.hlsl_bindless_rchit.txt compiled/triangle.hlsl_bindless_rchit.spv
+spirv-dis.exe -o compiled/triangle.hlsl_callable_rchit.txt compiled/triangle.hlsl_callable_rchit.spv
 spirv-dis.exe -o compiled/triangle.hlsl_lib.txt compiled/triangle.hlsl_lib.spv
 spirv-dis.exe -o compiled/triangle.hlsl_rchit.txt compiled/triangle.hlsl_rchit.spv
 spirv-dis.exe -o compiled/triangle.hlsl_rgen.txt compiled/triangle.hlsl_rgen.spv
 spirv-dis.exe -o compiled/triangle.hlsl_rmiss.txt compiled/triangle.hlsl_rmiss.spv
+spirv-dis.exe -o compiled/triangle.hlsl_shadow_rchit.txt compiled/triangle.hlsl_shadow_rchit.spv
 
 REM "** DONE **"
\ No newline at end of file
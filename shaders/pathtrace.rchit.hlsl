@@ -0,0 +1,59 @@
+struct Payload
+{
+    float3 albedo;
+    // Hit distance, or -1 when the ray missed and albedo is the sky
+    float t;
+    float3 normal;
+};
+
+struct Attribute
+{
+    float2 bary;
+};
+
+struct UniformBuffer
+{
+	float3 color;
+};
+
+ConstantBuffer<UniformBuffer> g_uniformBuffers[] : register(b2, space0);
+
+// Object space normals and triangle list indices of every mesh
+StructuredBuffer<float4> g_normals : register(t5, space0);
+StructuredBuffer<uint> g_indices : register(t6, space0);
+
+// GeometryRecord, the inline data of this geometry's hit record
+struct GeometryRecord
+{
+    uint firstVertex;
+    uint firstIndex;
+};
+
+[[vk::shader_record_nv]] ConstantBuffer<GeometryRecord> g_geometry;
+
+// Shared by the triangle and procedural hit groups
+[shader("closesthit")]
+void main(inout Payload payload : SV_RayPayload, in Attribute attribs : SV_IntersectionAttributes)
+{
+    float3 objectNormal;
+    if (HitKind() == HIT_KIND_TRIANGLE_FRONT_FACE || HitKind() == HIT_KIND_TRIANGLE_BACK_FACE)
+    {
+        const uint first = g_geometry.firstIndex + 3 * PrimitiveIndex();
+        const float3 n0 = g_normals[g_geometry.firstVertex + g_indices[first]].xyz;
+        const float3 n1 = g_normals[g_geometry.firstVertex + g_indices[first + 1]].xyz;
+        const float3 n2 = g_normals[g_geometry.firstVertex + g_indices[first + 2]].xyz;
+        const float3 barycentrics = float3(1.0 - attribs.bary.x - attribs.bary.y, attribs.bary.x, attribs.bary.y);
+        objectNormal = n0 * barycentrics.x + n1 * barycentrics.y + n2 * barycentrics.z;
+    }
+    else
+    {
+        // Unit sphere of sphere.rint.hlsl: the normal is the hit point
+        objectNormal = ObjectRayOrigin() + RayTCurrent() * ObjectRayDirection();
+    }
+
+    // InstanceID = GeometryInstance::instanceId
+    payload.albedo = g_uniformBuffers[NonUniformResourceIndex(InstanceID())].color.xyz;
+    payload.t = RayTCurrent();
+    // Normals transform by the inverse transpose of object to world
+    payload.normal = normalize(mul(objectNormal, (float3x3)WorldToObject3x4()));
+}
@@ -0,0 +1,124 @@
+struct Payload
+{
+    float3 albedo;
+    // Hit distance, or -1 when the ray missed and albedo is the sky
+    float t;
+    float3 normal;
+};
+
+RaytracingAccelerationStructure g_topLevel : register(t0, space0);
+RWTexture2D<float4> g_output : register(u1, space0);
+
+struct Camera
+{
+    float4x4 viewInverse;
+    float4x4 projectionInverse;
+};
+
+ConstantBuffer<Camera> g_camera : register(b3, space0);
+
+RWTexture2D<float4> g_accumulation : register(u4, space0);
+
+// PathTraceParams
+struct Params
+{
+    uint frameIndex;
+    uint maxBounces;
+};
+
+[[vk::push_constant]] ConstantBuffer<Params> g_params;
+
+static const float PI = 3.14159265;
+
+// PCG hash, see "Hash Functions for GPU Rendering" (Jarzynski and Olano)
+uint pcgHash(uint value)
+{
+    const uint state = value * 747796405u + 2891336453u;
+    const uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Uniform in [0, 1)
+float random(inout uint rng)
+{
+    rng = pcgHash(rng);
+    return (rng >> 8) * (1.0 / 16777216.0);
+}
+
+// Cosine-weighted direction in the hemisphere around normal. With this
+// pdf a Lambertian bounce scales the throughput by its albedo alone.
+float3 sampleHemisphere(float3 normal, inout uint rng)
+{
+    const float phi = 2.0 * PI * random(rng);
+    const float r2 = random(rng);
+    const float r = sqrt(r2);
+
+    const float3 helper = abs(normal.x) > 0.1 ? float3(0.0, 1.0, 0.0) : float3(1.0, 0.0, 0.0);
+    const float3 tangent = normalize(cross(helper, normal));
+    const float3 bitangent = cross(normal, tangent);
+    return normalize(tangent * (r * cos(phi)) + bitangent * (r * sin(phi)) + normal * sqrt(1.0 - r2));
+}
+
+[shader("raygeneration")]
+void main()
+{
+    uint2 launchIndex = DispatchRaysIndex().xy;
+    float2 dims = DispatchRaysDimensions().xy;
+
+    uint rng = pcgHash(launchIndex.y * uint(dims.x) + launchIndex.x + pcgHash(g_params.frameIndex));
+
+    // Jitter within the pixel so the average is antialiased
+    float2 pixel = launchIndex + float2(random(rng), random(rng));
+    float2 d = pixel / dims * 2.0 - 1.0;
+
+    float4 origin = mul(g_camera.viewInverse, float4(0.0, 0.0, 0.0, 1.0));
+    float4 target = mul(g_camera.projectionInverse, float4(d.x, d.y, 1.0, 1.0));
+    float4 direction = mul(g_camera.viewInverse, float4(normalize(target.xyz / target.w), 0.0));
+
+    RayDesc ray;
+    ray.Origin = origin.xyz;
+    ray.Direction = direction.xyz;
+    ray.TMin = 0.001;
+    ray.TMax = 1000.0;
+
+    float3 radiance = float3(0.0, 0.0, 0.0);
+    float3 throughput = float3(1.0, 1.0, 1.0);
+    for (uint bounce = 0; bounce <= g_params.maxBounces; ++bounce)
+    {
+        Payload payload;
+        payload.albedo = float3(0.0, 0.0, 0.0);
+        payload.t = -1.0;
+        payload.normal = float3(0.0, 0.0, 0.0);
+
+        // A geometry multiplier of 1, the only ray type, selects each
+        // geometry's own hit record
+        TraceRay(g_topLevel, RAY_FLAG_FORCE_OPAQUE, 0xff, 0, 1, 0, ray, payload);
+
+        if (payload.t < 0.0)
+        {
+            // The sky is the only light
+            radiance += throughput * payload.albedo;
+            break;
+        }
+
+        throughput *= payload.albedo;
+
+        // Bounce off the side the ray arrived from
+        float3 normal = payload.normal;
+        if (dot(normal, ray.Direction) > 0.0)
+            normal = -normal;
+
+        ray.Origin = ray.Origin + payload.t * ray.Direction;
+        ray.Direction = sampleHemisphere(normal, rng);
+    }
+
+    // Running mean over the frames since the last reset
+    float3 average = radiance;
+    if (g_params.frameIndex > 0)
+    {
+        const float3 previous = g_accumulation[launchIndex].rgb;
+        average = lerp(previous, radiance, 1.0 / (g_params.frameIndex + 1));
+    }
+    g_accumulation[launchIndex] = float4(average, 1.0);
+    g_output[launchIndex] = float4(saturate(average), 1.0);
+}
@@ -0,0 +1,17 @@
+struct Payload
+{
+    float3 albedo;
+    // Hit distance, or -1 when the ray missed and albedo is the sky
+    float t;
+    float3 normal;
+};
+
+// Sky gradient from the horizon to the zenith, lighting the scene
+[shader("miss")]
+void main(inout Payload payload : SV_RayPayload)
+{
+    const float height = saturate(normalize(WorldRayDirection()).y * 0.5 + 0.5);
+    payload.albedo = lerp(float3(1.0, 1.0, 1.0), float3(0.5, 0.7, 1.0), height);
+    payload.t = -1.0;
+    payload.normal = float3(0.0, 0.0, 0.0);
+}
@@ -0,0 +1,14 @@
+// Shared with triangle.callable.rchit.hlsl
+struct ShadeData
+{
+    float3 color;
+    float2 bary;
+};
+
+[shader("callable")]
+void main(inout ShadeData data)
+{
+    // Darken the material color toward the first vertex
+    const float3 barycentrics = float3(1.0 - data.bary.x - data.bary.y, data.bary.x, data.bary.y);
+    data.color *= 0.25 + 0.75 * (1.0 - barycentrics.x);
+}
@@ -0,0 +1,11 @@
+struct ShadowPayload
+{
+    // 1 until the ray misses everything on the way to the light
+    uint occluded;
+};
+
+[shader("miss")]
+void main(inout ShadowPayload payload : SV_RayPayload)
+{
+    payload.occluded = 0;
+}
@@ -0,0 +1,28 @@
+#version 460
+#extension GL_NV_ray_tracing : require
+
+hitAttributeNV vec3 attribs;
+
+// Unit sphere at the object space origin; place and scale it with the
+// instance transform. The closest hit shader receives the object space
+// normal at the hit point.
+void main()
+{
+    const vec3 origin = gl_ObjectRayOriginNV;
+    const vec3 direction = gl_ObjectRayDirectionNV;
+
+    const float a = dot(direction, direction);
+    const float b = dot(origin, direction);
+    const float c = dot(origin, origin) - 1.0;
+    const float discriminant = b * b - a * c;
+    if (discriminant < 0.0)
+        return;
+
+    const float root = sqrt(discriminant);
+    float t = (-b - root) / a;
+    if (t < gl_RayTminNV)
+        t = (-b + root) / a;
+
+    attribs = origin + t * direction;
+    reportIntersectionNV(t, 0);
+}
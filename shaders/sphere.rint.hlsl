@@ -0,0 +1,31 @@
+struct Attribute
+{
+    float2 bary;
+};
+
+// Unit sphere at the object space origin; place and scale it with the
+// instance transform. The closest hit shader receives the hit point's
+// longitude and latitude in [0, 1] in place of barycentrics.
+[shader("intersection")]
+void main()
+{
+    const float3 origin = ObjectRayOrigin();
+    const float3 direction = ObjectRayDirection();
+
+    const float a = dot(direction, direction);
+    const float b = dot(origin, direction);
+    const float c = dot(origin, origin) - 1.0;
+    const float discriminant = b * b - a * c;
+    if (discriminant < 0.0)
+        return;
+
+    const float root = sqrt(discriminant);
+    float t = (-b - root) / a;
+    if (t < RayTMin())
+        t = (-b + root) / a;
+
+    const float3 normal = origin + t * direction;
+    Attribute attribs;
+    attribs.bary = float2(atan2(normal.z, normal.x) * 0.5 / 3.14159265 + 0.5, normal.y * 0.5 + 0.5);
+    ReportHit(t, 0, attribs);
+}
@@ -0,0 +1,36 @@
+struct Payload
+{
+    float3 hitValue;
+};
+
+struct Attribute
+{
+    float2 bary;
+};
+
+struct UniformBuffer
+{
+	float3 color;
+};
+
+// Shared with shade.rcall.hlsl
+struct ShadeData
+{
+    float3 color;
+    float2 bary;
+};
+
+ConstantBuffer<UniformBuffer> g_uniformBuffers[] : register(b2, space0);
+
+[shader("closesthit")]
+void main(inout Payload payload : SV_RayPayload, in Attribute attribs : SV_IntersectionAttributes)
+{
+    ShadeData data;
+    data.color = g_uniformBuffers[NonUniformResourceIndex(InstanceID())].color.xyz;
+    data.bary = attribs.bary;
+
+    // Callable index 0 = the first RayTracingApp::add_callable_shader
+    CallShader(0, data);
+
+    payload.hitValue = data.color;
+}
@@ -11,6 +11,14 @@ struct Attribute
 RaytracingAccelerationStructure g_topLevel : register(t0, space0);
 RWTexture2D<float4> g_output : register(u1, space0);
 
+struct Camera
+{
+    float4x4 viewInverse;
+    float4x4 projectionInverse;
+};
+
+ConstantBuffer<Camera> g_camera : register(b3, space0);
+
 [shader("raygeneration")]
 void main()
 {
@@ -21,11 +29,14 @@ void main()
     float2 uv = pixelCenter / dims.xy;
 
     float2 d = uv * 2.0 - 1.0;
-    float aspectRatio = float(dims.x) / float(dims.y);
+
+    float4 origin = mul(g_camera.viewInverse, float4(0.0, 0.0, 0.0, 1.0));
+    float4 target = mul(g_camera.projectionInverse, float4(d.x, d.y, 1.0, 1.0));
+    float4 direction = mul(g_camera.viewInverse, float4(normalize(target.xyz / target.w), 0.0));
 
     RayDesc ray;
-    ray.Origin = float3(0.0, 0.0, -2.0);
-    ray.Direction = normalize(float3(d.x * aspectRatio, -d.y, 1.0));
+    ray.Origin = origin.xyz;
+    ray.Direction = direction.xyz;
     ray.TMin = 0.001;
     ray.TMax = 1000.0;
 
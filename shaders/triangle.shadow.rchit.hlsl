@@ -0,0 +1,52 @@
+struct Payload
+{
+    float3 hitValue;
+};
+
+struct ShadowPayload
+{
+    // 1 until the ray misses everything on the way to the light
+    uint occluded;
+};
+
+struct Attribute
+{
+    float2 bary;
+};
+
+RaytracingAccelerationStructure g_topLevel : register(t0, space0);
+
+struct UniformBuffer
+{
+	float3 color;
+};
+
+ConstantBuffer<UniformBuffer> g_uniformBuffers[] : register(b2, space0);
+
+// Direction towards a directional light, above and behind the camera
+static const float3 LIGHT_DIRECTION = normalize(float3(-0.5, 1.0, -1.0));
+
+// Ray types, matching ShaderConfig::ray_type_count
+static const uint SHADOW_RAY = 1;
+static const uint RAY_TYPE_COUNT = 2;
+
+[shader("closesthit")]
+void main(inout Payload payload : SV_RayPayload, in Attribute attribs : SV_IntersectionAttributes)
+{
+	// InstanceID = GeometryInstance::instanceId
+	const float3 color = g_uniformBuffers[NonUniformResourceIndex(InstanceID())].color.xyz;
+
+    RayDesc ray;
+    ray.Origin = WorldRayOrigin() + RayTCurrent() * WorldRayDirection();
+    ray.Direction = LIGHT_DIRECTION;
+    ray.TMin = 0.001;
+    ray.TMax = 1000.0;
+
+    // Any hit will do, and the shadow hit groups have no closest hit shader
+    ShadowPayload shadow;
+    shadow.occluded = 1;
+    TraceRay(g_topLevel, RAY_FLAG_ACCEPT_FIRST_HIT_AND_END_SEARCH | RAY_FLAG_SKIP_CLOSEST_HIT_SHADER | RAY_FLAG_FORCE_OPAQUE,
+        0xff, SHADOW_RAY, RAY_TYPE_COUNT, SHADOW_RAY, ray, shadow);
+
+    payload.hitValue = shadow.occluded != 0 ? color * 0.3 : color;
+}
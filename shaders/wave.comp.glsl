@@ -0,0 +1,29 @@
+#version 460
+
+// Ripples rest positions along y. Binding and push constant layout as
+// expected by VertexDeformer.
+
+layout(local_size_x = 64) in;
+
+layout(set = 0, binding = 0) readonly buffer RestVertices { float rest[]; };
+layout(set = 0, binding = 1) writeonly buffer Vertices { float deformed[]; };
+
+layout(push_constant) uniform DeformParams
+{
+    float time;
+    uint vertexCount;
+};
+
+void main()
+{
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= vertexCount)
+        return;
+
+    vec3 pos = vec3(rest[3 * index], rest[3 * index + 1], rest[3 * index + 2]);
+    pos.y += 0.1 * sin(2.0 * time + 4.0 * pos.x);
+
+    deformed[3 * index] = pos.x;
+    deformed[3 * index + 1] = pos.y;
+    deformed[3 * index + 2] = pos.z;
+}
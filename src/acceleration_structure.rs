@@ -0,0 +1,737 @@
+//! Acceleration structures with their bound device memory, and the
+//! geometry and instance layouts consumed by their builds.
+
+use crate::allocator::{Allocation, AllocationKind};
+use crate::base::{record_submit_commandbuffer, Base};
+use crate::error::{Error, Result};
+use crate::resource::BufferResource;
+use ash::extensions::nv;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::rc::Rc;
+
+/// Vertex layout of the triangle geometry (`R32G32B32_SFLOAT` positions).
+#[repr(C)]
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct Vertex {
+    pub pos: [f32; 3],
+}
+
+/// Bounds of one procedural primitive, laid out as AABB geometry builds
+/// expect them.
+#[repr(C)]
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+/// Instance record laid out as `VkGeometryInstanceNV` expects it in the
+/// top-level acceleration structure's instance buffer.
+#[repr(C)]
+#[derive(Clone, Debug, Copy)]
+pub struct GeometryInstance {
+    /// 3x4 row-major object-to-world transform.
+    pub transform: [f32; 12],
+    pub instance_id_and_mask: u32,
+    pub instance_offset_and_flags: u32,
+    pub acceleration_handle: u64,
+}
+
+impl GeometryInstance {
+    pub fn new(
+        transform: [f32; 12],
+        id: u32,
+        mask: u8,
+        offset: u32,
+        flags: vk::GeometryInstanceFlagsNV,
+        acceleration_handle: u64,
+    ) -> Self {
+        let mut instance = GeometryInstance {
+            transform,
+            instance_id_and_mask: 0,
+            instance_offset_and_flags: 0,
+            acceleration_handle,
+        };
+        instance.set_id(id);
+        instance.set_mask(mask);
+        instance.set_offset(offset);
+        instance.set_flags(flags);
+        instance
+    }
+
+    /// Sets the 24-bit custom index reported by `InstanceID()`.
+    pub fn set_id(&mut self, id: u32) {
+        let id = id & 0x00ff_ffff;
+        self.instance_id_and_mask |= id;
+    }
+
+    pub fn set_mask(&mut self, mask: u8) {
+        let mask = u32::from(mask);
+        self.instance_id_and_mask |= mask << 24;
+    }
+
+    /// Sets the 24-bit hit group offset into the shader binding table.
+    pub fn set_offset(&mut self, offset: u32) {
+        let offset = offset & 0x00ff_ffff;
+        self.instance_offset_and_flags |= offset;
+    }
+
+    pub fn set_flags(&mut self, flags: vk::GeometryInstanceFlagsNV) {
+        let flags = flags.as_raw();
+        self.instance_offset_and_flags |= flags << 24;
+    }
+}
+
+/// An acceleration structure bound to device local memory from the base's
+/// allocator.
+pub struct AccelerationStructure {
+    pub acceleration_structure: vk::AccelerationStructureNV,
+    pub allocation: Allocation,
+    /// Size of `allocation` in bytes.
+    pub size: vk::DeviceSize,
+    /// Opaque handle referenced by `GeometryInstance::acceleration_handle`.
+    pub handle: u64,
+    ray_tracing: Rc<nv::RayTracing>,
+    base: Rc<Base>,
+}
+
+impl AccelerationStructure {
+    /// Creates an acceleration structure sized for `info` and binds memory
+    /// to it. It still has to be built with `cmd_build_acceleration_structure`.
+    pub fn new(
+        info: vk::AccelerationStructureInfoNV,
+        ray_tracing: Rc<nv::RayTracing>,
+        base: Rc<Base>,
+    ) -> Result<Self> {
+        Self::with_compacted_size(info, 0, ray_tracing, base)
+    }
+
+    /// Creates the destination of a compacting copy. `compacted_size` comes
+    /// from an `ACCELERATION_STRUCTURE_COMPACTED_SIZE_NV` query, and `info`
+    /// must not list any geometry or instances.
+    pub fn with_compacted_size(
+        info: vk::AccelerationStructureInfoNV,
+        compacted_size: vk::DeviceSize,
+        ray_tracing: Rc<nv::RayTracing>,
+        base: Rc<Base>,
+    ) -> Result<Self> {
+        unsafe {
+            let create_info = vk::AccelerationStructureCreateInfoNV::builder()
+                .compacted_size(compacted_size)
+                .info(info)
+                .build();
+
+            let acceleration_structure =
+                ray_tracing.create_acceleration_structure(&create_info, None)?;
+
+            // Owning the handles right away lets Drop clean up on failure below.
+            let mut resource = AccelerationStructure {
+                acceleration_structure,
+                allocation: Allocation::default(),
+                size: 0,
+                handle: 0,
+                ray_tracing,
+                base,
+            };
+
+            let memory_requirements = resource
+                .memory_requirements(vk::AccelerationStructureMemoryRequirementsTypeNV::OBJECT);
+
+            // Acceleration structures live in buffer-like linear memory
+            resource.allocation = resource.base.allocator.allocate(
+                &resource.base.device,
+                &memory_requirements,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                AllocationKind::Linear,
+            )?;
+            resource.size = memory_requirements.size;
+
+            resource.ray_tracing.bind_acceleration_structure_memory(&[
+                vk::BindAccelerationStructureMemoryInfoNV::builder()
+                    .acceleration_structure(acceleration_structure)
+                    .memory(resource.allocation.memory)
+                    .memory_offset(resource.allocation.offset)
+                    .build(),
+            ])?;
+
+            resource.handle = resource
+                .ray_tracing
+                .get_acceleration_structure_handle(acceleration_structure)?;
+
+            Ok(resource)
+        }
+    }
+
+    /// Scratch buffer size needed to build from scratch.
+    pub fn build_scratch_size(&self) -> vk::DeviceSize {
+        self.memory_requirements(vk::AccelerationStructureMemoryRequirementsTypeNV::BUILD_SCRATCH)
+            .size
+    }
+
+    /// Scratch buffer size needed to update (refit) in place.
+    pub fn update_scratch_size(&self) -> vk::DeviceSize {
+        self.memory_requirements(vk::AccelerationStructureMemoryRequirementsTypeNV::UPDATE_SCRATCH)
+            .size
+    }
+
+    fn memory_requirements(
+        &self,
+        ty: vk::AccelerationStructureMemoryRequirementsTypeNV,
+    ) -> vk::MemoryRequirements {
+        unsafe {
+            self.ray_tracing
+                .get_acceleration_structure_memory_requirements(
+                    &vk::AccelerationStructureMemoryRequirementsInfoNV::builder()
+                        .acceleration_structure(self.acceleration_structure)
+                        .ty(ty)
+                        .build(),
+                )
+                .memory_requirements
+        }
+    }
+}
+
+impl Drop for AccelerationStructure {
+    fn drop(&mut self) {
+        unsafe {
+            self.ray_tracing
+                .destroy_acceleration_structure(self.acceleration_structure, None);
+            self.base
+                .allocator
+                .free(&self.base.device, &self.allocation);
+        }
+    }
+}
+
+/// Collects bottom-level geometry lists and instances that reference them by
+/// index, then builds every acceleration structure in one go.
+#[derive(Default)]
+pub struct SceneBuilder {
+    bottom_levels: Vec<BottomLevelGeometry>,
+    instances: Vec<(usize, GeometryInstance)>,
+    allow_compaction: bool,
+}
+
+#[derive(Clone)]
+struct BottomLevelGeometry {
+    geometry: Vec<vk::GeometryNV>,
+    /// Built with `ALLOW_UPDATE` and refitted by
+    /// `record_bottom_level_updates`.
+    updatable: bool,
+}
+
+/// Total bottom-level memory before and after compaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CompactionStats {
+    pub original_size: vk::DeviceSize,
+    pub compacted_size: vk::DeviceSize,
+}
+
+/// The acceleration structures built by a `SceneBuilder`.
+pub struct SceneAccelerationStructures {
+    pub bottom_levels: Vec<AccelerationStructure>,
+    pub top_level: AccelerationStructure,
+    /// The instances, with acceleration handles filled in. Changes take
+    /// effect once copied to the buffer passed to `record_top_level_update`.
+    pub instances: Vec<GeometryInstance>,
+    /// Set when the bottom levels were compacted.
+    pub compaction: Option<CompactionStats>,
+    top_level_info: vk::AccelerationStructureInfoNV,
+    /// Geometry and flags of the updatable bottom levels, by index.
+    bottom_level_updates: Vec<(
+        usize,
+        Vec<vk::GeometryNV>,
+        vk::BuildAccelerationStructureFlagsNV,
+    )>,
+    /// Sized for the top level and every updatable bottom level.
+    update_scratch_buffer: BufferResource,
+    ray_tracing: Rc<nv::RayTracing>,
+    base: Rc<Base>,
+}
+
+impl SceneAccelerationStructures {
+    /// Records an in-place refit of every bottom level added with
+    /// `add_updatable_bottom_level`, from the current contents of its
+    /// vertex buffers. Writes to those buffers must be made visible to
+    /// acceleration structure builds beforehand. Follow up with
+    /// `record_top_level_update` so the top level picks up the new bounds.
+    pub fn record_bottom_level_updates(&self, command_buffer: vk::CommandBuffer) {
+        if self.bottom_level_updates.is_empty() {
+            return;
+        }
+        unsafe {
+            // Earlier traces must be done reading the bottom levels.
+            self.base.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::RAY_TRACING_SHADER_NV,
+                vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_NV,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[],
+            );
+
+            for (index, geometry, flags) in &self.bottom_level_updates {
+                let info = vk::AccelerationStructureInfoNV::builder()
+                    .ty(vk::AccelerationStructureTypeNV::BOTTOM_LEVEL)
+                    .geometries(geometry)
+                    .flags(*flags)
+                    .build();
+                let bottom_level = &self.bottom_levels[*index];
+                self.ray_tracing.cmd_build_acceleration_structure(
+                    command_buffer,
+                    &info,
+                    vk::Buffer::null(),
+                    0,
+                    true,
+                    bottom_level.acceleration_structure,
+                    bottom_level.acceleration_structure,
+                    self.update_scratch_buffer.buffer,
+                    0,
+                );
+                // The next update reuses the scratch buffer, and the top
+                // level reads the result.
+                record_build_barrier(&self.base.device, command_buffer);
+            }
+        }
+    }
+
+    /// Records an in-place update of the top level from the instances in
+    /// `instance_buffer`, laid out like `instances`. Bottom levels and the
+    /// instance count stay the same. The buffer must not be rewritten until
+    /// the update has run, e.g. one buffer per frame in flight.
+    pub fn record_top_level_update(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        instance_buffer: vk::Buffer,
+    ) {
+        unsafe {
+            // Earlier traces must be done reading the top level.
+            self.base.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::RAY_TRACING_SHADER_NV,
+                vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_NV,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[],
+            );
+
+            self.ray_tracing.cmd_build_acceleration_structure(
+                command_buffer,
+                &self.top_level_info,
+                instance_buffer,
+                0,
+                true,
+                self.top_level.acceleration_structure,
+                self.top_level.acceleration_structure,
+                self.update_scratch_buffer.buffer,
+                0,
+            );
+
+            record_trace_barrier(&self.base.device, command_buffer);
+        }
+    }
+}
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        SceneBuilder::default()
+    }
+
+    /// Adds a bottom-level acceleration structure with one geometry per
+    /// entry and returns its index. The buffers `geometry` points at must
+    /// stay alive until `build` returns.
+    pub fn add_bottom_level(&mut self, geometry: Vec<vk::GeometryNV>) -> usize {
+        self.bottom_levels.push(BottomLevelGeometry {
+            geometry,
+            updatable: false,
+        });
+        self.bottom_levels.len() - 1
+    }
+
+    /// Like `add_bottom_level`, but built with `ALLOW_UPDATE` so it can be
+    /// refitted in place after its vertices move, see
+    /// `SceneAccelerationStructures::record_bottom_level_updates`. The
+    /// buffers must then stay alive as long as the acceleration structures.
+    pub fn add_updatable_bottom_level(&mut self, geometry: Vec<vk::GeometryNV>) -> usize {
+        self.bottom_levels.push(BottomLevelGeometry {
+            geometry,
+            updatable: true,
+        });
+        self.bottom_levels.len() - 1
+    }
+
+    /// Places the bottom level at index `bottom_level`. The instance's
+    /// `acceleration_handle` is filled in by `build`.
+    pub fn add_instance(&mut self, bottom_level: usize, instance: GeometryInstance) -> Result<()> {
+        if bottom_level >= self.bottom_levels.len() {
+            return Err(Error::UnknownBottomLevel {
+                index: bottom_level,
+                count: self.bottom_levels.len(),
+            });
+        }
+        self.instances.push((bottom_level, instance));
+        Ok(())
+    }
+
+    /// Opts in to compacting the bottom levels after they are built. This
+    /// costs an extra submission and a copy per bottom level, but usually
+    /// saves a good share of their memory.
+    pub fn set_compaction(&mut self, allow_compaction: bool) {
+        self.allow_compaction = allow_compaction;
+    }
+
+    /// Builds all bottom levels, then the top level, sharing a single
+    /// scratch buffer, and waits for the queue to finish. Fails with
+    /// `Error::EmptyScene` if no instances were added.
+    pub fn build(
+        &self,
+        ray_tracing: Rc<nv::RayTracing>,
+        base: Rc<Base>,
+    ) -> Result<SceneAccelerationStructures> {
+        if self.instances.is_empty() {
+            return Err(Error::EmptyScene);
+        }
+
+        let mut flags = vk::BuildAccelerationStructureFlagsNV::PREFER_FAST_TRACE;
+        if self.allow_compaction {
+            flags |= vk::BuildAccelerationStructureFlagsNV::ALLOW_COMPACTION;
+        }
+
+        // Create bottom-level acceleration structures
+
+        let bottom_level_info: Vec<vk::AccelerationStructureInfoNV> = self
+            .bottom_levels
+            .iter()
+            .map(|bottom_level| {
+                let mut flags = flags;
+                if bottom_level.updatable {
+                    flags |= vk::BuildAccelerationStructureFlagsNV::ALLOW_UPDATE;
+                }
+                vk::AccelerationStructureInfoNV::builder()
+                    .ty(vk::AccelerationStructureTypeNV::BOTTOM_LEVEL)
+                    .geometries(&bottom_level.geometry)
+                    .flags(flags)
+                    .build()
+            })
+            .collect();
+
+        let mut bottom_levels = bottom_level_info
+            .iter()
+            .map(|info| AccelerationStructure::new(*info, ray_tracing.clone(), base.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Create top-level acceleration structure
+
+        let top_level_info = vk::AccelerationStructureInfoNV::builder()
+            .ty(vk::AccelerationStructureTypeNV::TOP_LEVEL)
+            .instance_count(self.instances.len() as u32)
+            .flags(
+                vk::BuildAccelerationStructureFlagsNV::PREFER_FAST_TRACE
+                    | vk::BuildAccelerationStructureFlagsNV::ALLOW_UPDATE,
+            )
+            .build();
+
+        let top_level =
+            AccelerationStructure::new(top_level_info, ray_tracing.clone(), base.clone())?;
+
+        let scratch_buffer_size = bottom_levels
+            .iter()
+            .chain(std::iter::once(&top_level))
+            .map(AccelerationStructure::build_scratch_size)
+            .max()
+            .unwrap_or(0);
+
+        let scratch_buffer = BufferResource::new(
+            scratch_buffer_size,
+            vk::BufferUsageFlags::RAY_TRACING_NV,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            base.clone(),
+        )?;
+
+        let record_bottom_level_builds =
+            |command_buffer, bottom_levels: &[AccelerationStructure]| {
+                for (info, bottom_level) in bottom_level_info.iter().zip(bottom_levels) {
+                    unsafe {
+                        ray_tracing.cmd_build_acceleration_structure(
+                            command_buffer,
+                            info,
+                            vk::Buffer::null(),
+                            0,
+                            false,
+                            bottom_level.acceleration_structure,
+                            vk::AccelerationStructureNV::null(),
+                            scratch_buffer.buffer,
+                            0,
+                        );
+                    }
+                    // The next build reuses the scratch buffer.
+                    record_build_barrier(&base.device, command_buffer);
+                }
+            };
+
+        // Compact bottom levels, before their handles go into instances
+
+        let compaction = if self.allow_compaction && !bottom_levels.is_empty() {
+            let compacted_sizes = unsafe {
+                let query_pool = base.device.create_query_pool(
+                    &vk::QueryPoolCreateInfo::builder()
+                        .query_type(vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_NV)
+                        .query_count(bottom_levels.len() as u32),
+                    None,
+                )?;
+
+                let result = record_submit_commandbuffer(
+                    &base.device,
+                    base.command_buffer,
+                    base.present_queue,
+                    &[],
+                    &[],
+                    &[],
+                    |device, command_buffer| {
+                        device.cmd_reset_query_pool(
+                            command_buffer,
+                            query_pool,
+                            0,
+                            bottom_levels.len() as u32,
+                        );
+                        record_bottom_level_builds(command_buffer, &bottom_levels);
+                        let structures: Vec<vk::AccelerationStructureNV> = bottom_levels
+                            .iter()
+                            .map(|bottom_level| bottom_level.acceleration_structure)
+                            .collect();
+                        ray_tracing.cmd_write_acceleration_structures_properties(
+                            command_buffer,
+                            &structures,
+                            vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_NV,
+                            query_pool,
+                            0,
+                        );
+                    },
+                )
+                .and_then(|_| {
+                    let mut compacted_sizes = vec![0u64; bottom_levels.len()];
+                    base.device.get_query_pool_results(
+                        query_pool,
+                        0,
+                        bottom_levels.len() as u32,
+                        &mut compacted_sizes,
+                        vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+                    )?;
+                    Ok(compacted_sizes)
+                });
+
+                base.device.destroy_query_pool(query_pool, None);
+                result?
+            };
+
+            let compacted = bottom_level_info
+                .iter()
+                .zip(&compacted_sizes)
+                .map(|(info, &compacted_size)| {
+                    let info = vk::AccelerationStructureInfoNV::builder()
+                        .ty(info.ty)
+                        .flags(info.flags)
+                        .build();
+                    AccelerationStructure::with_compacted_size(
+                        info,
+                        compacted_size,
+                        ray_tracing.clone(),
+                        base.clone(),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            record_submit_commandbuffer(
+                &base.device,
+                base.command_buffer,
+                base.present_queue,
+                &[],
+                &[],
+                &[],
+                |_device, command_buffer| {
+                    for (dst, src) in compacted.iter().zip(&bottom_levels) {
+                        unsafe {
+                            ray_tracing.cmd_copy_acceleration_structure(
+                                command_buffer,
+                                dst.acceleration_structure,
+                                src.acceleration_structure,
+                                vk::CopyAccelerationStructureModeNV::COMPACT,
+                            );
+                        }
+                    }
+                },
+            )?;
+
+            let stats = CompactionStats {
+                original_size: bottom_levels.iter().map(|level| level.size).sum(),
+                compacted_size: compacted.iter().map(|level| level.size).sum(),
+            };
+            bottom_levels = compacted;
+            Some(stats)
+        } else {
+            None
+        };
+
+        // Kept for per-frame updates
+
+        let bottom_level_updates: Vec<_> = self
+            .bottom_levels
+            .iter()
+            .zip(&bottom_level_info)
+            .enumerate()
+            .filter(|(_, (bottom_level, _))| bottom_level.updatable)
+            .map(|(index, (bottom_level, info))| (index, bottom_level.geometry.clone(), info.flags))
+            .collect();
+
+        let update_scratch_size = bottom_level_updates
+            .iter()
+            .map(|(index, _, _)| bottom_levels[*index].update_scratch_size())
+            .chain(std::iter::once(top_level.update_scratch_size()))
+            .max()
+            .unwrap_or(0);
+
+        let update_scratch_buffer = BufferResource::new(
+            update_scratch_size.max(1),
+            vk::BufferUsageFlags::RAY_TRACING_NV,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            base.clone(),
+        )?;
+
+        // Create instance buffer
+
+        let instances: Vec<GeometryInstance> = self
+            .instances
+            .iter()
+            .map(|&(bottom_level, instance)| GeometryInstance {
+                acceleration_handle: bottom_levels[bottom_level].handle,
+                ..instance
+            })
+            .collect();
+
+        let instance_buffer_size = std::mem::size_of::<GeometryInstance>() * instances.len();
+        let mut instance_buffer = BufferResource::new(
+            instance_buffer_size as u64,
+            vk::BufferUsageFlags::RAY_TRACING_NV,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            base.clone(),
+        )?;
+        instance_buffer.store(&instances)?;
+
+        // Build the top level, and the bottom levels unless already built
+
+        record_submit_commandbuffer(
+            &base.device,
+            base.command_buffer,
+            base.present_queue,
+            &[],
+            &[],
+            &[],
+            |device, command_buffer| unsafe {
+                if compaction.is_none() {
+                    record_bottom_level_builds(command_buffer, &bottom_levels);
+                }
+
+                ray_tracing.cmd_build_acceleration_structure(
+                    command_buffer,
+                    &top_level_info,
+                    instance_buffer.buffer,
+                    0,
+                    false,
+                    top_level.acceleration_structure,
+                    vk::AccelerationStructureNV::null(),
+                    scratch_buffer.buffer,
+                    0,
+                );
+
+                record_trace_barrier(device, command_buffer);
+            },
+        )?;
+
+        Ok(SceneAccelerationStructures {
+            bottom_levels,
+            top_level,
+            instances,
+            compaction,
+            top_level_info,
+            bottom_level_updates,
+            update_scratch_buffer,
+            ray_tracing,
+            base,
+        })
+    }
+}
+
+/// Makes acceleration structure builds and copies wait for earlier ones,
+/// which they may read from or share scratch memory with.
+fn record_build_barrier<D: DeviceV1_0>(device: &D, command_buffer: vk::CommandBuffer) {
+    let memory_barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(
+            vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_NV
+                | vk::AccessFlags::ACCELERATION_STRUCTURE_READ_NV,
+        )
+        .dst_access_mask(
+            vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_NV
+                | vk::AccessFlags::ACCELERATION_STRUCTURE_READ_NV,
+        )
+        .build();
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_NV,
+            vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_NV,
+            vk::DependencyFlags::empty(),
+            &[memory_barrier],
+            &[],
+            &[],
+        );
+    }
+}
+
+/// Makes ray tracing wait for the top-level build or update before it.
+fn record_trace_barrier<D: DeviceV1_0>(device: &D, command_buffer: vk::CommandBuffer) {
+    let memory_barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_NV)
+        .dst_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_READ_NV)
+        .build();
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_NV,
+            vk::PipelineStageFlags::RAY_TRACING_SHADER_NV,
+            vk::DependencyFlags::empty(),
+            &[memory_barrier],
+            &[],
+            &[],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instances_must_reference_added_bottom_levels() {
+        let instance = GeometryInstance::new(
+            [0.0; 12],
+            0,
+            0xff,
+            0,
+            vk::GeometryInstanceFlagsNV::empty(),
+            0,
+        );
+        let mut builder = SceneBuilder::new();
+        match builder.add_instance(0, instance) {
+            Err(Error::UnknownBottomLevel { index: 0, count: 0 }) => {}
+            other => panic!("expected an unknown bottom level, got {:?}", other),
+        }
+        let bottom_level = builder.add_bottom_level(Vec::new());
+        builder.add_instance(bottom_level, instance).unwrap();
+        assert_eq!(builder.instances.len(), 1);
+    }
+}
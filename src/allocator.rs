@@ -0,0 +1,419 @@
+//! Sub-allocation of device memory from large blocks, so buffers, images and
+//! acceleration structures share a few `allocate_memory` calls instead of
+//! one each.
+
+use crate::error::{Error, Result};
+use crate::resource::find_memorytype_index;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+
+/// Size of the blocks allocations are carved from. Larger resources get a
+/// block of their own.
+pub const DEFAULT_BLOCK_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+
+/// How a resource lays out its memory. Linear and optimal resources must
+/// not share a `bufferImageGranularity` page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AllocationKind {
+    /// Buffers, acceleration structures and linear tiled images.
+    Linear,
+    /// Optimal tiled images.
+    Optimal,
+}
+
+impl AllocationKind {
+    pub fn of_tiling(tiling: vk::ImageTiling) -> Self {
+        if tiling == vk::ImageTiling::LINEAR {
+            AllocationKind::Linear
+        } else {
+            AllocationKind::Optimal
+        }
+    }
+}
+
+/// The range of a memory block bound to one resource. The default value
+/// holds no memory and is ignored by `MemoryAllocator::free`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    /// Where the resource is bound in `memory`.
+    pub offset: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+    /// Index into `MemoryAllocator::blocks`.
+    block: usize,
+}
+
+/// Memory held by a `MemoryAllocator`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MemoryStats {
+    /// Live `allocate_memory` calls, one per block.
+    pub block_count: usize,
+    pub allocation_count: usize,
+    /// Total size of the blocks.
+    pub reserved_bytes: vk::DeviceSize,
+    /// Bytes bound to resources, excluding alignment padding.
+    pub used_bytes: vk::DeviceSize,
+}
+
+/// Hands out ranges of blocks allocated per memory type. Blocks of the
+/// default size are kept until `destroy`, blocks made for a single large
+/// resource are freed with it.
+pub struct MemoryAllocator {
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    buffer_image_granularity: vk::DeviceSize,
+    block_size: vk::DeviceSize,
+    /// Freed dedicated blocks leave `None` behind, so indices stay valid.
+    blocks: RefCell<Vec<Option<MemoryBlock>>>,
+}
+
+struct MemoryBlock {
+    memory: vk::DeviceMemory,
+    memory_type_index: u32,
+    /// Made for one resource larger than the block size.
+    dedicated: bool,
+    ranges: FreeList,
+    /// Outstanding `map` calls; the block is mapped while nonzero.
+    map_count: u32,
+    mapped: *mut c_void,
+}
+
+impl MemoryAllocator {
+    pub fn new(
+        memory_properties: vk::PhysicalDeviceMemoryProperties,
+        buffer_image_granularity: vk::DeviceSize,
+        block_size: vk::DeviceSize,
+    ) -> Self {
+        MemoryAllocator {
+            memory_properties,
+            buffer_image_granularity,
+            block_size,
+            blocks: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Finds room for a resource with `requirements` in a block of a memory
+    /// type with `flags`, allocating a new block when none has any.
+    pub fn allocate(
+        &self,
+        device: &ash::Device,
+        requirements: &vk::MemoryRequirements,
+        flags: vk::MemoryPropertyFlags,
+        kind: AllocationKind,
+    ) -> Result<Allocation> {
+        let memory_type_index = find_memorytype_index(requirements, &self.memory_properties, flags)
+            .ok_or(Error::NoSuitableMemoryType)?;
+        let mut blocks = self.blocks.borrow_mut();
+
+        for (index, block) in blocks.iter_mut().enumerate() {
+            if let Some(block) = block {
+                if block.memory_type_index != memory_type_index || block.dedicated {
+                    continue;
+                }
+                if let Some(offset) =
+                    block
+                        .ranges
+                        .allocate(requirements.size, requirements.alignment, kind)
+                {
+                    return Ok(Allocation {
+                        memory: block.memory,
+                        offset,
+                        size: requirements.size,
+                        block: index,
+                    });
+                }
+            }
+        }
+
+        let dedicated = requirements.size > self.block_size;
+        let block_size = if dedicated {
+            requirements.size
+        } else {
+            self.block_size
+        };
+        let allocate_info = vk::MemoryAllocateInfo {
+            allocation_size: block_size,
+            memory_type_index,
+            ..Default::default()
+        };
+        let memory = unsafe { device.allocate_memory(&allocate_info, None)? };
+
+        let mut ranges = FreeList::new(block_size, self.buffer_image_granularity);
+        let offset = ranges
+            .allocate(requirements.size, requirements.alignment, kind)
+            .expect("a new block fits the resource it was sized for");
+        let block = MemoryBlock {
+            memory,
+            memory_type_index,
+            dedicated,
+            ranges,
+            map_count: 0,
+            mapped: std::ptr::null_mut(),
+        };
+        let index = match blocks.iter().position(Option::is_none) {
+            Some(index) => {
+                blocks[index] = Some(block);
+                index
+            }
+            None => {
+                blocks.push(Some(block));
+                blocks.len() - 1
+            }
+        };
+        Ok(Allocation {
+            memory,
+            offset,
+            size: requirements.size,
+            block: index,
+        })
+    }
+
+    /// Returns the range of `allocation` to its block, freeing the block if
+    /// it was dedicated to it. The resource bound there must be destroyed.
+    pub fn free(&self, device: &ash::Device, allocation: &Allocation) {
+        if allocation.memory == vk::DeviceMemory::null() {
+            return;
+        }
+        let mut blocks = self.blocks.borrow_mut();
+        let slot = &mut blocks[allocation.block];
+        if let Some(block) = slot {
+            block.ranges.free(allocation.offset);
+            if block.dedicated {
+                unsafe { device.free_memory(block.memory, None) };
+                *slot = None;
+            }
+        }
+    }
+
+    /// Maps the block of `allocation`, unless already mapped, and returns a
+    /// pointer to the allocation's first byte. The memory type must be host
+    /// visible. Pair every call with `unmap`.
+    pub fn map(&self, device: &ash::Device, allocation: &Allocation) -> Result<*mut c_void> {
+        let mut blocks = self.blocks.borrow_mut();
+        let block = blocks[allocation.block]
+            .as_mut()
+            .expect("mapping a freed allocation");
+        if block.map_count == 0 {
+            // A memory object can only be mapped once, so map all of it
+            // for every allocation in the block to share.
+            block.mapped = unsafe {
+                device.map_memory(block.memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())?
+            };
+        }
+        block.map_count += 1;
+        Ok(unsafe { (block.mapped as *mut u8).add(allocation.offset as usize) as *mut c_void })
+    }
+
+    /// Undoes one `map` of `allocation`, unmapping its block after the last.
+    pub fn unmap(&self, device: &ash::Device, allocation: &Allocation) {
+        let mut blocks = self.blocks.borrow_mut();
+        if let Some(block) = &mut blocks[allocation.block] {
+            block.map_count -= 1;
+            if block.map_count == 0 {
+                unsafe { device.unmap_memory(block.memory) };
+                block.mapped = std::ptr::null_mut();
+            }
+        }
+    }
+
+    /// Blocks and allocations across every memory type.
+    pub fn stats(&self) -> MemoryStats {
+        self.collect_stats(|_| true)
+    }
+
+    /// Blocks and allocations of the memory type at `memory_type_index`.
+    pub fn memory_type_stats(&self, memory_type_index: u32) -> MemoryStats {
+        self.collect_stats(|block| block.memory_type_index == memory_type_index)
+    }
+
+    fn collect_stats<F: Fn(&MemoryBlock) -> bool>(&self, filter: F) -> MemoryStats {
+        let blocks = self.blocks.borrow();
+        let mut stats = MemoryStats::default();
+        for block in blocks.iter().flatten().filter(|block| filter(block)) {
+            stats.block_count += 1;
+            stats.allocation_count += block.ranges.allocation_count();
+            stats.reserved_bytes += block.ranges.size;
+            stats.used_bytes += block.ranges.used_bytes();
+        }
+        stats
+    }
+
+    /// Frees every block. Everything allocated from them must be destroyed.
+    pub(crate) unsafe fn destroy(&self, device: &ash::Device) {
+        for block in self.blocks.borrow_mut().drain(..).flatten() {
+            device.free_memory(block.memory, None);
+        }
+    }
+}
+
+/// First fit placement of allocations within one block.
+#[derive(Debug)]
+struct FreeList {
+    size: vk::DeviceSize,
+    granularity: vk::DeviceSize,
+    /// Unused ranges as offset and size, sorted by offset. Neighbouring
+    /// ranges are merged, so a range is always bordered by allocations or
+    /// the ends of the block.
+    free: Vec<(vk::DeviceSize, vk::DeviceSize)>,
+    /// Size and kind of every allocation, by offset.
+    used: BTreeMap<vk::DeviceSize, (vk::DeviceSize, AllocationKind)>,
+}
+
+impl FreeList {
+    fn new(size: vk::DeviceSize, granularity: vk::DeviceSize) -> Self {
+        FreeList {
+            size,
+            granularity: granularity.max(1),
+            free: vec![(0, size)],
+            used: BTreeMap::new(),
+        }
+    }
+
+    /// Places `size` bytes at the lowest offset that is a multiple of
+    /// `alignment` and keeps resources of another kind off its pages.
+    fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+        kind: AllocationKind,
+    ) -> Option<vk::DeviceSize> {
+        let size = size.max(1);
+        let alignment = alignment.max(1);
+        for index in 0..self.free.len() {
+            let (start, free_size) = self.free[index];
+            let end = start + free_size;
+
+            let mut offset = align_up(start, alignment);
+            if let Some((&previous, &(previous_size, previous_kind))) =
+                self.used.range(..start).next_back()
+            {
+                if previous_kind != kind
+                    && same_page(previous + previous_size - 1, offset, self.granularity)
+                {
+                    offset = align_up(offset, self.granularity);
+                }
+            }
+            if offset + size > end {
+                continue;
+            }
+            if let Some((&next, &(_, next_kind))) = self.used.range(end..).next() {
+                if next_kind != kind && same_page(offset + size - 1, next, self.granularity) {
+                    continue;
+                }
+            }
+
+            let mut remainder = Vec::with_capacity(2);
+            if offset > start {
+                remainder.push((start, offset - start));
+            }
+            if offset + size < end {
+                remainder.push((offset + size, end - offset - size));
+            }
+            self.free.splice(index..=index, remainder);
+            self.used.insert(offset, (size, kind));
+            return Some(offset);
+        }
+        None
+    }
+
+    /// Returns the allocation at `offset`, merging it with free neighbours.
+    fn free(&mut self, offset: vk::DeviceSize) {
+        let (size, _) = self
+            .used
+            .remove(&offset)
+            .expect("freeing an offset that was never allocated");
+        let mut start = offset;
+        let mut end = offset + size;
+
+        let mut index = self.free.partition_point(|&(free, _)| free < offset);
+        if let Some(&(next, next_size)) = self.free.get(index) {
+            if next == end {
+                end += next_size;
+                self.free.remove(index);
+            }
+        }
+        if index > 0 {
+            let (previous, previous_size) = self.free[index - 1];
+            if previous + previous_size == start {
+                start = previous;
+                index -= 1;
+                self.free.remove(index);
+            }
+        }
+        self.free.insert(index, (start, end - start));
+    }
+
+    fn allocation_count(&self) -> usize {
+        self.used.len()
+    }
+
+    fn used_bytes(&self) -> vk::DeviceSize {
+        self.used.values().map(|&(size, _)| size).sum()
+    }
+}
+
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    value.div_ceil(alignment) * alignment
+}
+
+/// Whether the byte at `last_byte` and the one at `first_byte` share a
+/// page, as in the spec's `bufferImageGranularity` check.
+fn same_page(last_byte: vk::DeviceSize, first_byte: vk::DeviceSize, page: vk::DeviceSize) -> bool {
+    last_byte / page == first_byte / page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocations_are_aligned_and_first_fit() {
+        let mut list = FreeList::new(1024, 1);
+        assert_eq!(list.allocate(10, 1, AllocationKind::Linear), Some(0));
+        assert_eq!(list.allocate(16, 64, AllocationKind::Linear), Some(64));
+        // The padding in front of the aligned allocation is still free
+        assert_eq!(list.allocate(20, 4, AllocationKind::Linear), Some(12));
+        assert_eq!(list.allocate(1000, 1, AllocationKind::Linear), None);
+        assert_eq!(list.allocation_count(), 3);
+        assert_eq!(list.used_bytes(), 46);
+    }
+
+    #[test]
+    fn freed_ranges_merge_and_are_reused() {
+        let mut list = FreeList::new(256, 1);
+        let a = list.allocate(64, 1, AllocationKind::Linear).unwrap();
+        let b = list.allocate(64, 1, AllocationKind::Linear).unwrap();
+        let c = list.allocate(64, 1, AllocationKind::Linear).unwrap();
+        list.free(a);
+        list.free(c);
+        assert_eq!(list.free, vec![(0, 64), (128, 128)]);
+        list.free(b);
+        assert_eq!(list.free, vec![(0, 256)]);
+        assert_eq!(list.allocate(256, 1, AllocationKind::Linear), Some(0));
+    }
+
+    #[test]
+    fn kinds_do_not_share_granularity_pages() {
+        let mut list = FreeList::new(4096, 1024);
+        assert_eq!(list.allocate(100, 16, AllocationKind::Linear), Some(0));
+        // Same kind packs tightly, the other kind moves on to the next page
+        assert_eq!(list.allocate(100, 16, AllocationKind::Linear), Some(112));
+        assert_eq!(list.allocate(100, 16, AllocationKind::Optimal), Some(1024));
+
+        // Nor may it go in front of a resource of the other kind on the
+        // same page
+        list.free(0);
+        assert_eq!(list.allocate(50, 16, AllocationKind::Optimal), Some(1136));
+        assert_eq!(list.allocate(50, 16, AllocationKind::Linear), Some(0));
+    }
+
+    #[test]
+    fn align_up_rounds_to_multiples() {
+        assert_eq!(align_up(0, 256), 0);
+        assert_eq!(align_up(1, 256), 256);
+        assert_eq!(align_up(256, 256), 256);
+        assert_eq!(align_up(301, 12), 312);
+    }
+}
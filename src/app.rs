@@ -0,0 +1,1705 @@
+//! The triangle ray tracing renderer: scene, pipeline, descriptors and
+//! per-frame command recording.
+
+use crate::acceleration_structure::{
+    CompactionStats, GeometryInstance, SceneAccelerationStructures, SceneBuilder,
+};
+use crate::base::{record_submit_commandbuffer, Base};
+use crate::camera::{Camera, CameraUniform};
+use crate::compiler::ShaderLanguage;
+use crate::deform::VertexDeformer;
+use crate::error::{Error, Result};
+use crate::image_export::{texel_size, HostImage};
+use crate::mesh::MeshBuffers;
+use crate::path_tracing::{PathTraceParams, ShadingBuffers, ShadingData, DEFAULT_MAX_BOUNCES};
+use crate::pipeline::{create_descriptor_set_layouts, load_reflected_shader_module};
+use crate::pipeline_cache::PipelineCache;
+use crate::procedural::{aabb_geometry_flags, AabbBuffer};
+use crate::reflect::{merge_bindings, DescriptorBinding};
+use crate::resource::{BufferResource, ImageResource};
+use crate::scene::{Model, Scene};
+use crate::shader_binding_table::{ShaderBindingTable, ShaderBindingTableBuilder, ShaderRecord};
+use ash::extensions::nv;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::cell::Cell;
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Which of the bundled ray generation, closest hit and miss shaders
+/// `RayTracingApp` uses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShaderConfig {
+    pub language: ShaderLanguage,
+    /// Loads all three stages from the HLSL library `triangle.lib.hlsl`
+    /// instead of one module per stage. HLSL only.
+    pub library: bool,
+    /// Colors hits from per-material uniform buffers, indexed by instance
+    /// custom index through a runtime sized array at binding 2, instead of
+    /// by barycentrics. Shaders passed to `set_closest_hit_shader` or
+    /// `add_callable_shader` that read those buffers need this.
+    pub bindless: bool,
+    /// Replaces the bundled shaders with the progressive path tracer in
+    /// `pathtrace.*.hlsl`, which averages frames in an accumulation image
+    /// while the camera and scene stay still. HLSL only; takes precedence
+    /// over `library` and ignores `set_closest_hit_shader`.
+    pub path_tracing: bool,
+    /// Darkens material colors where a shadow ray toward a directional
+    /// light is blocked, using `triangle.shadow.rchit.hlsl` and a second
+    /// ray type with its own miss shader, `shadow.rmiss.hlsl`. HLSL only;
+    /// ignored with `library` or `path_tracing`, and ignores
+    /// `set_closest_hit_shader`.
+    pub shadows: bool,
+}
+
+impl ShaderConfig {
+    /// Whether the HLSL library is used.
+    pub fn uses_library(&self) -> bool {
+        self.library && self.language == ShaderLanguage::Hlsl && !self.uses_path_tracing()
+    }
+
+    /// Whether the path tracing shaders are used.
+    pub fn uses_path_tracing(&self) -> bool {
+        self.path_tracing && self.language == ShaderLanguage::Hlsl
+    }
+
+    /// Whether the shadow ray type and shaders are used.
+    pub fn uses_shadows(&self) -> bool {
+        self.shadows
+            && self.language == ShaderLanguage::Hlsl
+            && !self.uses_library()
+            && !self.uses_path_tracing()
+    }
+
+    /// Ray types traced by the shaders: primary rays, then shadow rays if
+    /// used. Each gets its own hit record per geometry and miss record, at
+    /// `RayContributionToHitGroupIndex` and `MissShaderIndex` equal to the
+    /// ray type.
+    pub fn ray_type_count(&self) -> u32 {
+        if self.uses_shadows() {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Whether the material uniform buffers are created and bound. The
+    /// path tracer and the shadow shader read their colors from them.
+    pub fn uses_material_buffers(&self) -> bool {
+        self.uses_path_tracing() || self.uses_shadows() || (self.bindless && !self.uses_library())
+    }
+}
+
+impl Default for ShaderConfig {
+    fn default() -> Self {
+        ShaderConfig {
+            language: ShaderLanguage::Hlsl,
+            library: false,
+            bindless: true,
+            path_tracing: false,
+            shadows: false,
+        }
+    }
+}
+
+/// Renders three triangles, or the scene passed to `set_scene`, into an
+/// offscreen storage image and blits the result to the swapchain.
+pub struct RayTracingApp {
+    base: Rc<Base>,
+    ray_tracing: Rc<nv::RayTracing>,
+    properties: vk::PhysicalDeviceRayTracingPropertiesNV,
+    shader_config: ShaderConfig,
+    /// Size of the offscreen target and of every frame traced into it.
+    extent: vk::Extent2D,
+    pipeline_cache_path: Option<PathBuf>,
+    pipeline_cache: Option<PipelineCache>,
+    camera: Camera,
+    frames_in_flight: usize,
+    /// Host visible buffers rewritten by `begin_frame`, one set per frame in
+    /// flight.
+    frame_resources: Vec<FrameResources>,
+    /// The frame slot selected by `begin_frame`.
+    frame: usize,
+    scene: Scene,
+    compact_acceleration_structures: bool,
+    acceleration_structures: Option<SceneAccelerationStructures>,
+    /// Set by `update_instances`, cleared once the update is recorded.
+    top_level_dirty: Cell<bool>,
+    /// Counts `update_instances` calls, so `begin_frame` knows which
+    /// instance buffers are out of date.
+    instances_version: u64,
+    deform_shader: Option<PathBuf>,
+    animation_time: f32,
+    /// Geometry of every model, kept for the deformer and BLAS refits.
+    mesh_buffers: Vec<Vec<MeshBuffers>>,
+    deformer: Option<VertexDeformer>,
+    /// Normals and indices of every mesh, and the hit records locating them.
+    shading_data: ShadingData,
+    /// `shading_data` on the device, for the path tracer only.
+    shading_buffers: Option<ShadingBuffers>,
+    /// RGBA32F running average of the path traced frames.
+    accumulation_target: Option<ImageResource>,
+    /// Frames averaged into `accumulation_target`. Reset whenever the
+    /// camera or scene changes, counted up by `record_offscreen`.
+    accumulated_frames: Cell<u32>,
+    max_bounces: u32,
+    /// Procedural hit group shaders; the sphere intersection shader when
+    /// not set.
+    intersection_shader: Option<PathBuf>,
+    any_hit_shader: Option<PathBuf>,
+    /// Replaces the closest hit shader of the triangle hit group.
+    closest_hit_shader: Option<PathBuf>,
+    callable_shaders: Vec<PathBuf>,
+    /// Reflected from the pipeline's shaders, merged across stages.
+    descriptor_bindings: Vec<DescriptorBinding>,
+    /// One per descriptor set; only set 0 is allocated and bound.
+    descriptor_set_layouts: Vec<vk::DescriptorSetLayout>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    shader_binding_table: Option<ShaderBindingTable>,
+    color_buffers: Vec<BufferResource>,
+    descriptor_pool: vk::DescriptorPool,
+    /// One per frame in flight, differing only in the camera buffer.
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    offscreen_target: ImageResource,
+    rgen_shader_module: vk::ShaderModule,
+    chit_shader_module: vk::ShaderModule,
+    miss_shader_module: vk::ShaderModule,
+    lib_shader_module: vk::ShaderModule,
+    rint_shader_module: vk::ShaderModule,
+    ahit_shader_module: vk::ShaderModule,
+    shadow_miss_shader_module: vk::ShaderModule,
+    rcall_shader_modules: Vec<vk::ShaderModule>,
+}
+
+impl RayTracingApp {
+    pub fn new(
+        base: Rc<Base>,
+        ray_tracing: Rc<nv::RayTracing>,
+        properties: vk::PhysicalDeviceRayTracingPropertiesNV,
+        shader_config: ShaderConfig,
+    ) -> Self {
+        RayTracingApp {
+            base: base.clone(),
+            ray_tracing,
+            properties,
+            shader_config,
+            extent: vk::Extent2D {
+                width: base.window_width,
+                height: base.window_height,
+            },
+            pipeline_cache_path: None,
+            pipeline_cache: None,
+            camera: Camera::default(),
+            frames_in_flight: 1,
+            frame_resources: Vec::new(),
+            frame: 0,
+            scene: Scene::triangles(),
+            compact_acceleration_structures: false,
+            acceleration_structures: None,
+            top_level_dirty: Cell::new(false),
+            instances_version: 0,
+            deform_shader: None,
+            animation_time: 0.0,
+            mesh_buffers: Vec::new(),
+            deformer: None,
+            shading_data: ShadingData::default(),
+            shading_buffers: None,
+            accumulation_target: None,
+            accumulated_frames: Cell::new(0),
+            max_bounces: DEFAULT_MAX_BOUNCES,
+            intersection_shader: None,
+            any_hit_shader: None,
+            closest_hit_shader: None,
+            callable_shaders: Vec::new(),
+            descriptor_bindings: Vec::new(),
+            descriptor_set_layouts: Vec::new(),
+            pipeline_layout: vk::PipelineLayout::null(),
+            pipeline: vk::Pipeline::null(),
+            shader_binding_table: None,
+            color_buffers: Vec::new(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            descriptor_sets: Vec::new(),
+            offscreen_target: ImageResource::new(base),
+            rgen_shader_module: vk::ShaderModule::null(),
+            chit_shader_module: vk::ShaderModule::null(),
+            miss_shader_module: vk::ShaderModule::null(),
+            lib_shader_module: vk::ShaderModule::null(),
+            rint_shader_module: vk::ShaderModule::null(),
+            ahit_shader_module: vk::ShaderModule::null(),
+            shadow_miss_shader_module: vk::ShaderModule::null(),
+            rcall_shader_modules: Vec::new(),
+        }
+    }
+
+    /// Replaces the default triangles with `scene`: one bottom-level
+    /// acceleration structure per model, one color buffer per material.
+    /// Must be called before `initialize`.
+    pub fn set_scene(&mut self, scene: Scene) {
+        self.scene = scene;
+    }
+
+    /// Compacts the bottom-level acceleration structures after building
+    /// them. Must be called before `initialize`.
+    pub fn set_compaction(&mut self, enabled: bool) {
+        self.compact_acceleration_structures = enabled;
+    }
+
+    /// Seeds the pipeline cache from `path` and writes it back there on
+    /// `release`, so later runs create the ray tracing pipeline faster.
+    /// Must be called before `initialize`.
+    pub fn set_pipeline_cache_path(&mut self, path: &Path) {
+        self.pipeline_cache_path = Some(path.to_owned());
+    }
+
+    /// Gives each of `count` frames in flight its own camera buffer,
+    /// instance buffer and descriptor set, selected with `begin_frame`.
+    /// One unless set. Must be called before `initialize`.
+    ///
+    /// This lets the CPU record a frame while the GPU runs the previous one.
+    /// The GPU itself still runs frames one after another: they share the
+    /// top-level acceleration structure, the offscreen target and the
+    /// accumulation image, ordered by barriers.
+    pub fn set_frames_in_flight(&mut self, count: usize) {
+        self.frames_in_flight = count.max(1);
+    }
+
+    /// Runs the compute shader at `shader_path` over the vertices of every
+    /// model marked `deformable` at the start of each frame, and refits
+    /// their bottom levels and the top level after it. See `VertexDeformer`
+    /// for the shader interface. Must be called before `initialize`.
+    pub fn set_deformer(&mut self, shader_path: &Path) {
+        self.deform_shader = Some(shader_path.to_owned());
+    }
+
+    /// Replaces the sphere intersection shader of the procedural hit group,
+    /// used by every model with `aabbs`. With an any-hit shader the AABB
+    /// geometry is no longer built as `OPAQUE`. Must be called before
+    /// `initialize`.
+    pub fn set_procedural_shaders(&mut self, intersection: &Path, any_hit: Option<&Path>) {
+        self.intersection_shader = Some(intersection.to_owned());
+        self.any_hit_shader = any_hit.map(Path::to_owned);
+    }
+
+    /// Replaces the closest hit shader of the triangle hit group, e.g. with
+    /// one that shades through callable shaders. Must be called before
+    /// `initialize`; ignored by the HLSL library shaders.
+    pub fn set_closest_hit_shader(&mut self, path: &Path) {
+        self.closest_hit_shader = Some(path.to_owned());
+    }
+
+    /// Adds a callable shader and returns the index `CallShader` selects it
+    /// with. Must be called before `initialize`.
+    pub fn add_callable_shader(&mut self, path: &Path) -> u32 {
+        self.callable_shaders.push(path.to_owned());
+        self.callable_shaders.len() as u32 - 1
+    }
+
+    /// Moves the camera rays are traced from. Takes effect with the next
+    /// `begin_frame`; may be called before `initialize`.
+    pub fn set_camera(&mut self, camera: &Camera) {
+        if self.camera != *camera {
+            self.accumulated_frames.set(0);
+        }
+        self.camera = *camera;
+    }
+
+    /// Bounces traced per path tracing sample, `DEFAULT_MAX_BOUNCES` unless
+    /// set. Restarts the accumulation.
+    pub fn set_max_bounces(&mut self, bounces: u32) {
+        self.max_bounces = bounces;
+        self.accumulated_frames.set(0);
+    }
+
+    /// Time passed to the deformation shader by the next recorded frame.
+    pub fn set_animation_time(&mut self, seconds: f32) {
+        self.animation_time = seconds;
+    }
+
+    /// Lets `f` change the instances of the top-level acceleration structure,
+    /// typically their transforms. The top level is updated in place by the
+    /// frame recorded after the next `begin_frame`. Does nothing before
+    /// `initialize`.
+    pub fn update_instances<F: FnOnce(&mut [GeometryInstance])>(&mut self, f: F) {
+        if let Some(acceleration_structures) = &mut self.acceleration_structures {
+            f(&mut acceleration_structures.instances);
+            self.instances_version += 1;
+            self.top_level_dirty.set(true);
+            self.accumulated_frames.set(0);
+        }
+    }
+
+    /// Bottom-level memory before and after compaction, if it was enabled.
+    pub fn compaction_stats(&self) -> Option<CompactionStats> {
+        self.acceleration_structures
+            .as_ref()
+            .and_then(|acceleration_structures| acceleration_structures.compaction)
+    }
+
+    /// Selects the per-frame resources of slot `frame` for the next recorded
+    /// frame and writes the camera, and the instances if they changed, to
+    /// them. The slot's previous frame must have finished on the GPU, e.g.
+    /// after `FrameManager::next_frame` returned it.
+    pub fn begin_frame(&mut self, frame: usize) -> Result<()> {
+        self.frame = frame;
+        let uniform = self.camera_uniform();
+        let resources = &mut self.frame_resources[frame];
+        resources.camera_buffer.store(&[uniform])?;
+        if resources.instances_version != self.instances_version {
+            if let Some(acceleration_structures) = &self.acceleration_structures {
+                resources
+                    .instance_buffer
+                    .store(&acceleration_structures.instances)?;
+            }
+            resources.instances_version = self.instances_version;
+        }
+        Ok(())
+    }
+
+    /// Creates every GPU resource the app needs. Must be called once before
+    /// recording any command buffers.
+    pub fn initialize(&mut self) -> Result<()> {
+        self.pipeline_cache = Some(PipelineCache::new(
+            self.pipeline_cache_path.as_deref(),
+            self.base.clone(),
+        )?);
+        self.create_offscreen_target()?;
+        self.shading_data = ShadingData::new(&self.scene);
+        self.create_acceleration_structures()?;
+        self.create_bindless_uniform_buffers()?;
+        if self.shader_config.uses_path_tracing() {
+            self.shading_buffers =
+                Some(ShadingBuffers::new(&self.shading_data, self.base.clone())?);
+        }
+        self.create_frame_resources()?;
+        self.create_pipeline()?;
+        self.create_shader_binding_table()?;
+        self.create_descriptor_sets()
+    }
+
+    /// Destroys everything created by `initialize`, after waiting for the
+    /// device to go idle, then saves the pipeline cache. Resources are
+    /// destroyed even if the wait fails; the cache is only saved if it
+    /// succeeds.
+    pub fn release(&mut self) -> Result<()> {
+        unsafe {
+            let result = self.base.device.device_wait_idle();
+
+            self.deformer = None;
+            self.acceleration_structures = None;
+            self.mesh_buffers.clear();
+
+            self.take_pipeline_objects().destroy(&self.base.device);
+
+            self.color_buffers.clear();
+            self.frame_resources.clear();
+            self.shading_buffers = None;
+            self.accumulation_target = None;
+
+            let saved = match self.pipeline_cache.take() {
+                Some(pipeline_cache) if result.is_ok() => pipeline_cache.save(),
+                _ => Ok(()),
+            };
+
+            result?;
+            saved
+        }
+    }
+
+    /// Reloads every shader and rebuilds the pipeline, shader binding table
+    /// and descriptor sets around the existing acceleration structures, e.g.
+    /// after a `ShaderWatcher` reports changes. If any step fails the
+    /// previous pipeline stays in use and the error is returned.
+    pub fn reload_shaders(&mut self) -> Result<()> {
+        unsafe { self.base.device.device_wait_idle()? };
+
+        let previous = self.take_pipeline_objects();
+        let result = self
+            .create_pipeline()
+            .and_then(|_| self.create_shader_binding_table())
+            .and_then(|_| self.create_descriptor_sets());
+        match result {
+            Ok(()) => {
+                previous.destroy(&self.base.device);
+                self.accumulated_frames.set(0);
+                Ok(())
+            }
+            Err(err) => {
+                self.take_pipeline_objects().destroy(&self.base.device);
+                self.restore_pipeline_objects(previous);
+                Err(err)
+            }
+        }
+    }
+
+    /// Recreates the offscreen target, and the accumulation image when path
+    /// tracing, at `extent` and points the descriptor sets at them, e.g.
+    /// after `Base::recreate_swapchain`. Restarts the accumulation.
+    pub fn resize(&mut self, extent: vk::Extent2D) -> Result<()> {
+        if extent.width == self.extent.width && extent.height == self.extent.height {
+            return Ok(());
+        }
+        unsafe { self.base.device.device_wait_idle()? };
+
+        self.extent = extent;
+        self.offscreen_target = ImageResource::new(self.base.clone());
+        self.accumulation_target = None;
+        self.create_offscreen_target()?;
+        self.write_storage_image_descriptors();
+        self.accumulated_frames.set(0);
+        Ok(())
+    }
+
+    /// Moves the shader-dependent objects out of the app, leaving null
+    /// handles behind.
+    fn take_pipeline_objects(&mut self) -> PipelineObjects {
+        PipelineObjects {
+            shader_modules: [
+                std::mem::take(&mut self.rgen_shader_module),
+                std::mem::take(&mut self.chit_shader_module),
+                std::mem::take(&mut self.miss_shader_module),
+                std::mem::take(&mut self.lib_shader_module),
+                std::mem::take(&mut self.rint_shader_module),
+                std::mem::take(&mut self.ahit_shader_module),
+                std::mem::take(&mut self.shadow_miss_shader_module),
+            ],
+            rcall_shader_modules: std::mem::take(&mut self.rcall_shader_modules),
+            descriptor_bindings: std::mem::take(&mut self.descriptor_bindings),
+            descriptor_set_layouts: std::mem::take(&mut self.descriptor_set_layouts),
+            pipeline_layout: std::mem::take(&mut self.pipeline_layout),
+            pipeline: std::mem::take(&mut self.pipeline),
+            shader_binding_table: self.shader_binding_table.take(),
+            descriptor_pool: std::mem::take(&mut self.descriptor_pool),
+            descriptor_sets: std::mem::take(&mut self.descriptor_sets),
+        }
+    }
+
+    fn restore_pipeline_objects(&mut self, objects: PipelineObjects) {
+        let [rgen, chit, miss, lib, rint, ahit, shadow_miss] = objects.shader_modules;
+        self.rgen_shader_module = rgen;
+        self.chit_shader_module = chit;
+        self.miss_shader_module = miss;
+        self.lib_shader_module = lib;
+        self.rint_shader_module = rint;
+        self.ahit_shader_module = ahit;
+        self.shadow_miss_shader_module = shadow_miss;
+        self.rcall_shader_modules = objects.rcall_shader_modules;
+        self.descriptor_bindings = objects.descriptor_bindings;
+        self.descriptor_set_layouts = objects.descriptor_set_layouts;
+        self.pipeline_layout = objects.pipeline_layout;
+        self.pipeline = objects.pipeline;
+        self.shader_binding_table = objects.shader_binding_table;
+        self.descriptor_pool = objects.descriptor_pool;
+        self.descriptor_sets = objects.descriptor_sets;
+    }
+
+    fn create_offscreen_target(&mut self) -> Result<()> {
+        self.offscreen_target.create_image(
+            vk::ImageType::TYPE_2D,
+            self.base.surface_format.format,
+            vk::Extent3D::builder()
+                .width(self.extent.width)
+                .height(self.extent.height)
+                .depth(1)
+                .build(),
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+
+        self.offscreen_target.create_view(
+            vk::ImageViewType::TYPE_2D,
+            self.base.surface_format.format,
+            color_subresource_range(),
+        )?;
+
+        if self.shader_config.uses_path_tracing() {
+            self.create_accumulation_target()?;
+        }
+        Ok(())
+    }
+
+    /// Creates the accumulation image and moves it to `GENERAL` layout,
+    /// where it stays; each frame reads the previous average back.
+    fn create_accumulation_target(&mut self) -> Result<()> {
+        let format = vk::Format::R32G32B32A32_SFLOAT;
+        let mut accumulation_target = ImageResource::new(self.base.clone());
+        accumulation_target.create_image(
+            vk::ImageType::TYPE_2D,
+            format,
+            vk::Extent3D::builder()
+                .width(self.extent.width)
+                .height(self.extent.height)
+                .depth(1)
+                .build(),
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::STORAGE,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        accumulation_target.create_view(
+            vk::ImageViewType::TYPE_2D,
+            format,
+            color_subresource_range(),
+        )?;
+
+        record_submit_commandbuffer(
+            &self.base.device,
+            self.base.command_buffer,
+            self.base.present_queue,
+            &[],
+            &[],
+            &[],
+            |_device, command_buffer| {
+                self.record_image_barrier(
+                    command_buffer,
+                    accumulation_target.image,
+                    color_subresource_range(),
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::GENERAL,
+                )
+            },
+        )?;
+        self.accumulation_target = Some(accumulation_target);
+        Ok(())
+    }
+
+    fn create_acceleration_structures(&mut self) -> Result<()> {
+        // Create geometry, one per mesh and a list per model. Deformable
+        // models only get device local vertices with a deformation shader.
+
+        let deformable: Vec<bool> = self
+            .scene
+            .models
+            .iter()
+            .map(|model| model.deformable && !model.is_procedural() && self.deform_shader.is_some())
+            .collect();
+
+        let model_buffers = self
+            .scene
+            .models
+            .iter()
+            .zip(&deformable)
+            .map(|(model, &deformable)| {
+                let meshes: &[_] = if model.is_procedural() {
+                    &[]
+                } else {
+                    &model.meshes
+                };
+                meshes
+                    .iter()
+                    .map(|mesh| {
+                        if deformable {
+                            MeshBuffers::new_deformable(mesh, self.base.clone())
+                        } else {
+                            MeshBuffers::new(mesh, self.base.clone())
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let aabb_buffers = self
+            .scene
+            .models
+            .iter()
+            .map(|model| {
+                if model.is_procedural() {
+                    AabbBuffer::new(&model.aabbs, self.base.clone()).map(Some)
+                } else {
+                    Ok(None)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let aabb_flags = aabb_geometry_flags(self.any_hit_shader.is_some());
+        let model_geometry: Vec<Vec<vk::GeometryNV>> = model_buffers
+            .iter()
+            .zip(&aabb_buffers)
+            .map(|(buffers, aabb_buffer)| match aabb_buffer {
+                Some(aabb_buffer) => vec![aabb_buffer.geometry(aabb_flags)],
+                None => buffers.iter().map(MeshBuffers::geometry).collect(),
+            })
+            .collect();
+
+        if let (true, Some(shader_path)) = (deformable.contains(&true), &self.deform_shader) {
+            let deformable_buffers: Vec<&MeshBuffers> = model_buffers.iter().flatten().collect();
+            let deformer =
+                VertexDeformer::new(shader_path, &deformable_buffers, self.base.clone())?;
+
+            // The deformed vertices are undefined until the first pass.
+            record_submit_commandbuffer(
+                &self.base.device,
+                self.base.command_buffer,
+                self.base.present_queue,
+                &[],
+                &[],
+                &[],
+                |_device, command_buffer| deformer.record(command_buffer, self.animation_time),
+            )?;
+            self.deformer = Some(deformer);
+        }
+
+        let mut builder = SceneBuilder::new();
+        builder.set_compaction(self.compact_acceleration_structures);
+        for (geometry, &deformable) in model_geometry.into_iter().zip(&deformable) {
+            if deformable {
+                builder.add_updatable_bottom_level(geometry);
+            } else {
+                builder.add_bottom_level(geometry);
+            }
+        }
+        // Each model's hit records start at its first geometry's record,
+        // with one record per ray type for every geometry
+        let ray_type_count = self.shader_config.ray_type_count();
+        let first_hit_records = self.shading_data.first_hit_records();
+        for instance in &self.scene.instances {
+            // Unknown models are reported by `add_instance`
+            let first_hit_record = first_hit_records.get(instance.model).copied().unwrap_or(0);
+            builder.add_instance(
+                instance.model,
+                GeometryInstance::new(
+                    instance.transform,
+                    instance.custom_id,
+                    0xff,
+                    first_hit_record * ray_type_count,
+                    vk::GeometryInstanceFlagsNV::TRIANGLE_CULL_DISABLE,
+                    0,
+                ),
+            )?;
+        }
+
+        self.acceleration_structures =
+            Some(builder.build(self.ray_tracing.clone(), self.base.clone())?);
+        self.mesh_buffers = model_buffers;
+        Ok(())
+    }
+
+    fn create_pipeline(&mut self) -> Result<()> {
+        // Closest hit shaders tracing shadow rays add a level of recursion
+        let max_recursion_depth = if self.shader_config.uses_shadows() {
+            2
+        } else {
+            1
+        };
+        if max_recursion_depth > self.properties.max_recursion_depth {
+            return Err(Error::RecursionDepthTooLarge {
+                depth: max_recursion_depth,
+                max: self.properties.max_recursion_depth,
+            });
+        }
+
+        // Descriptor set layouts follow the bindings the shaders declare
+        let mut reflections = Vec::new();
+        let device = &self.base.device;
+        unsafe {
+            let config = self.shader_config;
+            let lang = config.language;
+            if config.uses_library() {
+                let lib_path = default_shader_path("triangle", lang, None, "lib");
+                let (module, reflection) = load_reflected_shader_module(device, &lib_path)?;
+                self.lib_shader_module = module;
+                reflections.push(reflection);
+            } else {
+                let variant = if config.bindless {
+                    Some("bindless")
+                } else {
+                    None
+                };
+
+                let (rgen_path, rchit_path, rmiss_path) = if config.uses_path_tracing() {
+                    (
+                        default_shader_path("pathtrace", lang, None, "rgen"),
+                        default_shader_path("pathtrace", lang, None, "rchit"),
+                        default_shader_path("pathtrace", lang, None, "rmiss"),
+                    )
+                } else if config.uses_shadows() {
+                    (
+                        default_shader_path("triangle", lang, None, "rgen"),
+                        default_shader_path("triangle", lang, Some("shadow"), "rchit"),
+                        default_shader_path("triangle", lang, None, "rmiss"),
+                    )
+                } else {
+                    (
+                        default_shader_path("triangle", lang, None, "rgen"),
+                        match &self.closest_hit_shader {
+                            Some(path) => path.clone(),
+                            None => default_shader_path("triangle", lang, variant, "rchit"),
+                        },
+                        default_shader_path("triangle", lang, None, "rmiss"),
+                    )
+                };
+
+                let (module, reflection) = load_reflected_shader_module(device, &rgen_path)?;
+                self.rgen_shader_module = module;
+                reflections.push(reflection);
+                let (module, reflection) = load_reflected_shader_module(device, &rchit_path)?;
+                self.chit_shader_module = module;
+                reflections.push(reflection);
+                let (module, reflection) = load_reflected_shader_module(device, &rmiss_path)?;
+                self.miss_shader_module = module;
+                reflections.push(reflection);
+            }
+
+            if self.has_procedural_hit_group() {
+                let rint_path = match &self.intersection_shader {
+                    Some(path) => path.clone(),
+                    None => default_shader_path("sphere", lang, None, "rint"),
+                };
+                let (module, reflection) = load_reflected_shader_module(device, &rint_path)?;
+                self.rint_shader_module = module;
+                reflections.push(reflection);
+                if let Some(ahit_path) = &self.any_hit_shader {
+                    let (module, reflection) = load_reflected_shader_module(device, ahit_path)?;
+                    self.ahit_shader_module = module;
+                    reflections.push(reflection);
+                }
+            }
+
+            if config.uses_shadows() {
+                let shadow_miss_path = default_shader_path("shadow", lang, None, "rmiss");
+                let (module, reflection) = load_reflected_shader_module(device, &shadow_miss_path)?;
+                self.shadow_miss_shader_module = module;
+                reflections.push(reflection);
+            }
+
+            for path in &self.callable_shaders {
+                let (module, reflection) = load_reflected_shader_module(device, path)?;
+                self.rcall_shader_modules.push(module);
+                reflections.push(reflection);
+            }
+
+            self.descriptor_bindings = merge_bindings(&reflections)?;
+            self.descriptor_set_layouts = create_descriptor_set_layouts(
+                device,
+                &self.descriptor_bindings,
+                self.color_buffers.len() as u32,
+            )?;
+
+            // Only the path tracing ray generation shader reads these
+            let mut push_constant_ranges = Vec::new();
+            if config.uses_path_tracing() {
+                push_constant_ranges.push(
+                    vk::PushConstantRange::builder()
+                        .stage_flags(vk::ShaderStageFlags::RAYGEN_NV)
+                        .size(std::mem::size_of::<PathTraceParams>() as u32)
+                        .build(),
+                );
+            }
+            let layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+                .set_layouts(&self.descriptor_set_layouts)
+                .push_constant_ranges(&push_constant_ranges);
+
+            self.pipeline_layout = device.create_pipeline_layout(&layout_create_info, None)?;
+
+            let mut shader_stages = if config.uses_library() {
+                vec![
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::RAYGEN_NV)
+                        .module(self.lib_shader_module)
+                        .name(CStr::from_bytes_with_nul(b"rgen_main\0").unwrap())
+                        .build(),
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::CLOSEST_HIT_NV)
+                        .module(self.lib_shader_module)
+                        .name(CStr::from_bytes_with_nul(b"rchit_main\0").unwrap())
+                        .build(),
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::MISS_NV)
+                        .module(self.lib_shader_module)
+                        .name(CStr::from_bytes_with_nul(b"rmiss_main\0").unwrap())
+                        .build(),
+                ]
+            } else {
+                vec![
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::RAYGEN_NV)
+                        .module(self.rgen_shader_module)
+                        .name(CStr::from_bytes_with_nul(b"main\0").unwrap())
+                        .build(),
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::CLOSEST_HIT_NV)
+                        .module(self.chit_shader_module)
+                        .name(CStr::from_bytes_with_nul(b"main\0").unwrap())
+                        .build(),
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::MISS_NV)
+                        .module(self.miss_shader_module)
+                        .name(CStr::from_bytes_with_nul(b"main\0").unwrap())
+                        .build(),
+                ]
+            };
+
+            if self.has_procedural_hit_group() {
+                shader_stages.push(
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::INTERSECTION_NV)
+                        .module(self.rint_shader_module)
+                        .name(CStr::from_bytes_with_nul(b"main\0").unwrap())
+                        .build(),
+                );
+                if self.any_hit_shader.is_some() {
+                    shader_stages.push(
+                        vk::PipelineShaderStageCreateInfo::builder()
+                            .stage(vk::ShaderStageFlags::ANY_HIT_NV)
+                            .module(self.ahit_shader_module)
+                            .name(CStr::from_bytes_with_nul(b"main\0").unwrap())
+                            .build(),
+                    );
+                }
+            }
+
+            let shadow_miss_shader = if config.uses_shadows() {
+                shader_stages.push(
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::MISS_NV)
+                        .module(self.shadow_miss_shader_module)
+                        .name(CStr::from_bytes_with_nul(b"main\0").unwrap())
+                        .build(),
+                );
+                Some(shader_stages.len() as u32 - 1)
+            } else {
+                None
+            };
+
+            // group0 = [ raygen ]
+            let mut shader_groups = vec![general_shader_group(0)];
+            let any_hit_shader = if self.any_hit_shader.is_some() {
+                4
+            } else {
+                vk::SHADER_UNUSED_NV
+            };
+            // then per ray type = [ chit ], [ chit, rint, ahit ] if procedural
+            for ray_type in 0..config.ray_type_count() {
+                // Shadow rays only need to know whether anything was hit
+                let closest_hit_shader = if ray_type == 0 {
+                    1
+                } else {
+                    vk::SHADER_UNUSED_NV
+                };
+                shader_groups.push(
+                    vk::RayTracingShaderGroupCreateInfoNV::builder()
+                        .ty(vk::RayTracingShaderGroupTypeNV::TRIANGLES_HIT_GROUP)
+                        .general_shader(vk::SHADER_UNUSED_NV)
+                        .closest_hit_shader(closest_hit_shader)
+                        .any_hit_shader(vk::SHADER_UNUSED_NV)
+                        .intersection_shader(vk::SHADER_UNUSED_NV)
+                        .build(),
+                );
+                if self.has_procedural_hit_group() {
+                    shader_groups.push(
+                        vk::RayTracingShaderGroupCreateInfoNV::builder()
+                            .ty(vk::RayTracingShaderGroupTypeNV::PROCEDURAL_HIT_GROUP)
+                            .general_shader(vk::SHADER_UNUSED_NV)
+                            .closest_hit_shader(closest_hit_shader)
+                            .any_hit_shader(any_hit_shader)
+                            .intersection_shader(3)
+                            .build(),
+                    );
+                }
+            }
+            // then per ray type = [ miss ]
+            shader_groups.push(general_shader_group(2));
+            shader_groups.extend(shadow_miss_shader.map(general_shader_group));
+
+            // groups after miss = [ callable ], one each
+            for &module in &self.rcall_shader_modules {
+                shader_groups.push(general_shader_group(shader_stages.len() as u32));
+                shader_stages.push(
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::CALLABLE_NV)
+                        .module(module)
+                        .name(CStr::from_bytes_with_nul(b"main\0").unwrap())
+                        .build(),
+                );
+            }
+
+            let pipeline_cache = self
+                .pipeline_cache
+                .as_ref()
+                .map_or(vk::PipelineCache::null(), |pipeline_cache| {
+                    pipeline_cache.cache
+                });
+            self.pipeline = self.ray_tracing.create_ray_tracing_pipelines(
+                pipeline_cache,
+                &[vk::RayTracingPipelineCreateInfoNV::builder()
+                    .stages(&shader_stages)
+                    .groups(&shader_groups)
+                    .max_recursion_depth(max_recursion_depth)
+                    .layout(self.pipeline_layout)
+                    .build()],
+                None,
+            )?[0];
+        }
+        Ok(())
+    }
+
+    /// Whether any model needs the procedural hit group, which follows the
+    /// triangle hit group of each ray type.
+    fn has_procedural_hit_group(&self) -> bool {
+        self.scene.models.iter().any(Model::is_procedural)
+    }
+
+    /// Hit groups per ray type.
+    fn hit_group_count(&self) -> u32 {
+        if self.has_procedural_hit_group() {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn create_shader_binding_table(&mut self) -> Result<()> {
+        // Group indices as listed in vk::RayTracingPipelineCreateInfoNV
+        let ray_type_count = self.shader_config.ray_type_count();
+        let mut builder = ShaderBindingTableBuilder::new();
+        builder.add_raygen(ShaderRecord::new(0));
+        // One hit record per ray type per geometry, in model order. Shaders
+        // tracing with a geometry multiplier of 0 only ever use each model's
+        // first geometry.
+        for (model, records) in self
+            .scene
+            .models
+            .iter()
+            .zip(&self.shading_data.geometry_records)
+        {
+            let hit_group = if model.is_procedural() { 1 } else { 0 };
+            for record in records {
+                for ray_type in 0..ray_type_count {
+                    let group = 1 + ray_type * self.hit_group_count() + hit_group;
+                    builder.add_hit(ShaderRecord::with_data(group, record));
+                }
+            }
+        }
+        let first_miss = 1 + ray_type_count * self.hit_group_count();
+        for ray_type in 0..ray_type_count {
+            builder.add_miss(ShaderRecord::new(first_miss + ray_type));
+        }
+        for callable in 0..self.callable_shaders.len() as u32 {
+            builder.add_callable(ShaderRecord::new(first_miss + ray_type_count + callable));
+        }
+
+        self.shader_binding_table = Some(builder.build(
+            self.base.clone(),
+            &self.ray_tracing,
+            &self.properties,
+            self.pipeline,
+        )?);
+        Ok(())
+    }
+
+    /// Whether a shader of the pipeline declares `binding` in set 0.
+    fn has_binding(&self, binding: u32) -> bool {
+        self.descriptor_bindings
+            .iter()
+            .any(|declared| declared.set == 0 && declared.binding == binding)
+    }
+
+    fn create_frame_resources(&mut self) -> Result<()> {
+        let instances = &self.acceleration_structures.as_ref().unwrap().instances;
+        for _ in 0..self.frames_in_flight {
+            let mut camera_buffer = BufferResource::new(
+                std::mem::size_of::<CameraUniform>() as vk::DeviceSize,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                self.base.clone(),
+            )?;
+            camera_buffer.store(&[self.camera_uniform()])?;
+
+            let mut instance_buffer = BufferResource::new(
+                (std::mem::size_of::<GeometryInstance>() * instances.len()) as vk::DeviceSize,
+                vk::BufferUsageFlags::RAY_TRACING_NV,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                self.base.clone(),
+            )?;
+            instance_buffer.store(instances)?;
+
+            self.frame_resources.push(FrameResources {
+                camera_buffer,
+                instance_buffer,
+                instances_version: self.instances_version,
+            });
+        }
+        Ok(())
+    }
+
+    fn camera_uniform(&self) -> CameraUniform {
+        let aspect = self.extent.width as f32 / self.extent.height as f32;
+        self.camera.uniform(aspect)
+    }
+
+    fn create_bindless_uniform_buffers(&mut self) -> Result<()> {
+        if !self.shader_config.uses_material_buffers() {
+            return Ok(());
+        }
+        let buffer_size = (std::mem::size_of::<f32>() * 3) as vk::DeviceSize;
+
+        for color in &self.scene.materials {
+            let mut color_buffer = BufferResource::new(
+                buffer_size,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+                self.base.clone(),
+            )?;
+            color_buffer.store(color)?;
+            self.color_buffers.push(color_buffer);
+        }
+        Ok(())
+    }
+
+    fn create_descriptor_sets(&mut self) -> Result<()> {
+        let set_count = self.frame_resources.len() as u32;
+        unsafe {
+            // Enough for the reflected bindings of set 0, once per frame
+            let runtime_array_count = self.color_buffers.len() as u32;
+            let descriptor_sizes: Vec<vk::DescriptorPoolSize> = self
+                .descriptor_bindings
+                .iter()
+                .filter(|binding| binding.set == 0)
+                .map(|binding| vk::DescriptorPoolSize {
+                    ty: binding.descriptor_type,
+                    descriptor_count: set_count
+                        * if binding.is_runtime_array() {
+                            runtime_array_count
+                        } else {
+                            binding.count
+                        },
+                })
+                .collect();
+
+            let descriptor_pool_info = vk::DescriptorPoolCreateInfo::builder()
+                .pool_sizes(&descriptor_sizes)
+                .max_sets(set_count);
+
+            self.descriptor_pool = self
+                .base
+                .device
+                .create_descriptor_pool(&descriptor_pool_info, None)?;
+
+            let has_runtime_array = self
+                .descriptor_bindings
+                .iter()
+                .any(|binding| binding.set == 0 && binding.is_runtime_array());
+            let variable_counts = vec![
+                if has_runtime_array {
+                    runtime_array_count
+                } else {
+                    0
+                };
+                set_count as usize
+            ];
+            let set_layouts = vec![self.descriptor_set_layouts[0]; set_count as usize];
+            let mut variable_count_info =
+                vk::DescriptorSetVariableDescriptorCountAllocateInfoEXT::builder()
+                    .descriptor_counts(&variable_counts)
+                    .build();
+            self.descriptor_sets = self.base.device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(self.descriptor_pool)
+                    .set_layouts(&set_layouts)
+                    .push_next(&mut variable_count_info)
+                    .build(),
+            )?;
+        }
+        for frame in 0..self.descriptor_sets.len() {
+            self.write_buffer_descriptors(frame);
+        }
+        self.write_storage_image_descriptors();
+        Ok(())
+    }
+
+    /// Points the descriptor set of `frame` at the top level, the material
+    /// and shading buffers and the frame's camera buffer.
+    fn write_buffer_descriptors(&self, frame: usize) {
+        let descriptor_set = self.descriptor_sets[frame];
+        unsafe {
+            let accel_structs = [self
+                .acceleration_structures
+                .as_ref()
+                .unwrap()
+                .top_level
+                .acceleration_structure];
+            let mut accel_info = vk::WriteDescriptorSetAccelerationStructureNV::builder()
+                .acceleration_structures(&accel_structs)
+                .build();
+
+            let mut accel_write = vk::WriteDescriptorSet::builder()
+                .dst_set(descriptor_set)
+                .dst_binding(0)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_NV)
+                .push_next(&mut accel_info)
+                .build();
+
+            // This is only set by the builder for images, buffers, or views; need to set explicitly after
+            accel_write.descriptor_count = 1;
+
+            // Update descriptors for bindless uniform buffers
+
+            let buffer_info: Vec<vk::DescriptorBufferInfo> = self
+                .color_buffers
+                .iter()
+                .map(|color_buffer| {
+                    vk::DescriptorBufferInfo::builder()
+                        .buffer(color_buffer.buffer)
+                        .range(vk::WHOLE_SIZE)
+                        .build()
+                })
+                .collect();
+
+            let buffers_write = vk::WriteDescriptorSet::builder()
+                .dst_set(descriptor_set)
+                .dst_binding(2)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .buffer_info(&buffer_info)
+                .build();
+
+            let camera_info = [vk::DescriptorBufferInfo::builder()
+                .buffer(self.frame_resources[frame].camera_buffer.buffer)
+                .range(vk::WHOLE_SIZE)
+                .build()];
+
+            let camera_write = vk::WriteDescriptorSet::builder()
+                .dst_set(descriptor_set)
+                .dst_binding(3)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .buffer_info(&camera_info)
+                .build();
+
+            // Only the bindless closest hit shaders declare binding 2, and
+            // SPIR-V built before the camera existed lacks binding 3
+            let mut writes = vec![accel_write];
+            if self.has_binding(2) && !buffer_info.is_empty() {
+                writes.push(buffers_write);
+            }
+            if self.has_binding(3) {
+                writes.push(camera_write);
+            }
+
+            // The path tracer's shading buffers
+            let shading_info: Vec<[vk::DescriptorBufferInfo; 1]> = self
+                .shading_buffers
+                .iter()
+                .flat_map(|shading_buffers| {
+                    vec![
+                        &shading_buffers.normal_buffer,
+                        &shading_buffers.index_buffer,
+                    ]
+                })
+                .map(|buffer| {
+                    [vk::DescriptorBufferInfo::builder()
+                        .buffer(buffer.buffer)
+                        .range(vk::WHOLE_SIZE)
+                        .build()]
+                })
+                .collect();
+            for (binding, info) in (5..).zip(&shading_info) {
+                if self.has_binding(binding) {
+                    writes.push(
+                        vk::WriteDescriptorSet::builder()
+                            .dst_set(descriptor_set)
+                            .dst_binding(binding)
+                            .dst_array_element(0)
+                            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                            .buffer_info(info)
+                            .build(),
+                    );
+                }
+            }
+            self.base.device.update_descriptor_sets(&writes, &[]);
+        }
+    }
+
+    /// Points bindings 1 and, when path tracing, 4 of every descriptor set at
+    /// the offscreen target and the accumulation image. Separate from
+    /// `create_descriptor_sets` since both images are recreated by `resize`.
+    fn write_storage_image_descriptors(&self) {
+        for &descriptor_set in &self.descriptor_sets {
+            self.write_storage_image_descriptor_set(descriptor_set);
+        }
+    }
+
+    fn write_storage_image_descriptor_set(&self, descriptor_set: vk::DescriptorSet) {
+        let image_info = [vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::GENERAL)
+            .image_view(self.offscreen_target.view)
+            .build()];
+
+        let image_write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(1)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+            .image_info(&image_info)
+            .build();
+
+        let accumulation_info: Vec<vk::DescriptorImageInfo> = self
+            .accumulation_target
+            .iter()
+            .map(|accumulation_target| {
+                vk::DescriptorImageInfo::builder()
+                    .image_layout(vk::ImageLayout::GENERAL)
+                    .image_view(accumulation_target.view)
+                    .build()
+            })
+            .collect();
+
+        let mut writes = vec![image_write];
+        if self.has_binding(4) && !accumulation_info.is_empty() {
+            writes.push(
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(descriptor_set)
+                    .dst_binding(4)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                    .image_info(&accumulation_info)
+                    .build(),
+            );
+        }
+        unsafe { self.base.device.update_descriptor_sets(&writes, &[]) };
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn record_image_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+        src_access: vk::AccessFlags,
+        dst_access: vk::AccessFlags,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+    ) {
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .image(image)
+            .subresource_range(range)
+            .build();
+        unsafe {
+            self.base.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            );
+        }
+    }
+
+    /// Traces the scene into the offscreen target, leaving it in `GENERAL`
+    /// layout, with the resources selected by `begin_frame`. This is all a
+    /// headless frame needs.
+    pub fn record_offscreen(&self, command_buffer: vk::CommandBuffer) {
+        if let Some(acceleration_structures) = &self.acceleration_structures {
+            let deformed = match &self.deformer {
+                Some(deformer) => {
+                    deformer.record(command_buffer, self.animation_time);
+                    acceleration_structures.record_bottom_level_updates(command_buffer);
+                    true
+                }
+                None => false,
+            };
+            if self.top_level_dirty.replace(false) || deformed {
+                acceleration_structures.record_top_level_update(
+                    command_buffer,
+                    self.frame_resources[self.frame].instance_buffer.buffer,
+                );
+            }
+            if deformed {
+                self.accumulated_frames.set(0);
+            }
+        }
+
+        if let Some(accumulation_target) = &self.accumulation_target {
+            // Make the previous frame's average visible to this one
+            self.record_image_barrier(
+                command_buffer,
+                accumulation_target.image,
+                color_subresource_range(),
+                vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                vk::ImageLayout::GENERAL,
+                vk::ImageLayout::GENERAL,
+            );
+        }
+
+        self.record_image_barrier(
+            command_buffer,
+            self.offscreen_target.image,
+            color_subresource_range(),
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::SHADER_WRITE,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::GENERAL,
+        );
+
+        self.record_ray_tracing(command_buffer);
+        self.accumulated_frames
+            .set(self.accumulated_frames.get().saturating_add(1));
+    }
+
+    /// Traces the scene into the offscreen target and blits it to
+    /// `present_image`, leaving the latter in `PRESENT_SRC_KHR`.
+    pub fn record_command_buffer(
+        &self,
+        present_image: vk::Image,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        let range = color_subresource_range();
+
+        self.record_offscreen(command_buffer);
+
+        self.record_image_barrier(
+            command_buffer,
+            present_image,
+            range,
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        );
+
+        self.record_image_barrier(
+            command_buffer,
+            self.offscreen_target.image,
+            range,
+            vk::AccessFlags::SHADER_WRITE,
+            vk::AccessFlags::TRANSFER_READ,
+            vk::ImageLayout::GENERAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        );
+
+        let region = vk::ImageBlit::builder()
+            .src_subresource(
+                vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1)
+                    .build(),
+            )
+            .dst_subresource(
+                vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1)
+                    .build(),
+            )
+            .src_offsets([
+                vk::Offset3D::default(),
+                vk::Offset3D::builder()
+                    .x(self.extent.width as i32)
+                    .y(self.extent.height as i32)
+                    .z(1)
+                    .build(),
+            ])
+            .dst_offsets([
+                vk::Offset3D::default(),
+                vk::Offset3D::builder()
+                    .x(self.extent.width as i32)
+                    .y(self.extent.height as i32)
+                    .z(1)
+                    .build(),
+            ])
+            .build();
+
+        unsafe {
+            self.base.device.cmd_blit_image(
+                command_buffer,
+                self.offscreen_target.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                present_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+                vk::Filter::NEAREST,
+            );
+        }
+
+        self.record_image_barrier(
+            command_buffer,
+            present_image,
+            range,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::empty(),
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+        );
+    }
+
+    /// Copies the offscreen target, as left in `GENERAL` layout by
+    /// `record_offscreen`, into `buffer` and makes the copy visible to the
+    /// host. `buffer` needs `TRANSFER_DST` usage and room for every texel.
+    pub fn record_offscreen_readback(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        buffer: &BufferResource,
+    ) {
+        self.record_image_barrier(
+            command_buffer,
+            self.offscreen_target.image,
+            color_subresource_range(),
+            vk::AccessFlags::SHADER_WRITE,
+            vk::AccessFlags::TRANSFER_READ,
+            vk::ImageLayout::GENERAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        );
+
+        let region = vk::BufferImageCopy::builder()
+            .image_subresource(
+                vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1)
+                    .build(),
+            )
+            .image_extent(vk::Extent3D {
+                width: self.extent.width,
+                height: self.extent.height,
+                depth: 1,
+            })
+            .build();
+
+        let host_barrier = vk::MemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .build();
+
+        unsafe {
+            self.base.device.cmd_copy_image_to_buffer(
+                command_buffer,
+                self.offscreen_target.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer.buffer,
+                &[region],
+            );
+            self.base.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[host_barrier],
+                &[],
+                &[],
+            );
+        }
+    }
+
+    /// Renders one frame offscreen in frame slot 0, blocking until the GPU
+    /// is done. With path tracing, each call adds a sample per pixel to the
+    /// accumulation before `capture_offscreen`.
+    pub fn render_offscreen(&mut self) -> Result<()> {
+        self.begin_frame(0)?;
+        record_submit_commandbuffer(
+            &self.base.device,
+            self.base.command_buffer,
+            self.base.present_queue,
+            &[],
+            &[],
+            &[],
+            |_device, command_buffer| self.record_offscreen(command_buffer),
+        )
+    }
+
+    /// Renders one frame offscreen in frame slot 0 and reads it back as
+    /// RGBA8, blocking until the GPU is done.
+    pub fn capture_offscreen(&mut self) -> Result<HostImage> {
+        self.begin_frame(0)?;
+
+        let format = self.base.surface_format.format;
+        let texel_size = texel_size(format).ok_or(Error::UnsupportedFormat(format))?;
+        let size = u64::from(self.extent.width) * u64::from(self.extent.height) * texel_size as u64;
+
+        let mut readback_buffer = BufferResource::new(
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            self.base.clone(),
+        )?;
+
+        record_submit_commandbuffer(
+            &self.base.device,
+            self.base.command_buffer,
+            self.base.present_queue,
+            &[],
+            &[],
+            &[],
+            |_device, command_buffer| {
+                self.record_offscreen(command_buffer);
+                self.record_offscreen_readback(command_buffer, &readback_buffer);
+            },
+        )?;
+
+        HostImage::from_raw(
+            self.extent.width,
+            self.extent.height,
+            format,
+            &readback_buffer.load()?,
+        )
+        .ok_or(Error::UnsupportedFormat(format))
+    }
+
+    fn record_ray_tracing(&self, command_buffer: vk::CommandBuffer) {
+        if let Some(ref shader_binding_table) = self.shader_binding_table {
+            // |[ raygen ]|[ miss ]|[ hit groups ... ]|[ callables ... ]|
+            // Each region starts at a multiple of shader_group_base_alignment.
+            let layout = &shader_binding_table.layout;
+
+            let sbt_raygen_buffer = shader_binding_table.region_buffer(&layout.raygen);
+            let sbt_raygen_offset = layout.raygen.record_offset(0);
+
+            let sbt_miss_buffer = shader_binding_table.region_buffer(&layout.miss);
+            let sbt_miss_offset = layout.miss.offset;
+            let sbt_miss_stride = layout.miss.stride;
+
+            let sbt_hit_buffer = shader_binding_table.region_buffer(&layout.hit);
+            let sbt_hit_offset = layout.hit.offset;
+            let sbt_hit_stride = layout.hit.stride;
+
+            let sbt_call_buffer = shader_binding_table.region_buffer(&layout.callable);
+            let sbt_call_offset = layout.callable.offset;
+            let sbt_call_stride = layout.callable.stride;
+
+            unsafe {
+                self.base.device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::RAY_TRACING_NV,
+                    self.pipeline,
+                );
+                self.base.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::RAY_TRACING_NV,
+                    self.pipeline_layout,
+                    0,
+                    &[self.descriptor_sets[self.frame]],
+                    &[],
+                );
+                if self.shader_config.uses_path_tracing() {
+                    let params = PathTraceParams {
+                        frame_index: self.accumulated_frames.get(),
+                        max_bounces: self.max_bounces,
+                    };
+                    self.base.device.cmd_push_constants(
+                        command_buffer,
+                        self.pipeline_layout,
+                        vk::ShaderStageFlags::RAYGEN_NV,
+                        0,
+                        std::slice::from_raw_parts(
+                            &params as *const PathTraceParams as *const u8,
+                            std::mem::size_of::<PathTraceParams>(),
+                        ),
+                    );
+                }
+                self.ray_tracing.cmd_trace_rays(
+                    command_buffer,
+                    sbt_raygen_buffer,
+                    sbt_raygen_offset,
+                    sbt_miss_buffer,
+                    sbt_miss_offset,
+                    sbt_miss_stride,
+                    sbt_hit_buffer,
+                    sbt_hit_offset,
+                    sbt_hit_stride,
+                    sbt_call_buffer,
+                    sbt_call_offset,
+                    sbt_call_stride,
+                    self.extent.width,
+                    self.extent.height,
+                    1,
+                )
+            }
+        }
+    }
+}
+
+/// Path of one of the bundled shaders. With a runtime compiler for `lang`
+/// this is the source under `shaders/`, so `reload_shaders` picks up edits
+/// directly; otherwise it is the SPIR-V built by `shaders/compile.sh`.
+fn default_shader_path(
+    name: &str,
+    language: ShaderLanguage,
+    variant: Option<&str>,
+    stage: &str,
+) -> PathBuf {
+    let (lang, compiled_at_runtime) = match language {
+        ShaderLanguage::Hlsl => ("hlsl", cfg!(feature = "hlsl-compiler")),
+        ShaderLanguage::Glsl => ("glsl", cfg!(feature = "glsl-compiler")),
+    };
+    if compiled_at_runtime {
+        let variant = variant.map_or(String::new(), |variant| format!("{}.", variant));
+        PathBuf::from(format!("shaders/{}.{}{}.{}", name, variant, stage, lang))
+    } else {
+        let variant = variant.map_or(String::new(), |variant| format!("{}_", variant));
+        PathBuf::from(format!(
+            "shaders/compiled/{}.{}_{}{}.spv",
+            name, lang, variant, stage
+        ))
+    }
+}
+
+/// The host visible buffers of one frame in flight. Only these are
+/// duplicated; the GPU resources frames write are shared, see
+/// `set_frames_in_flight`.
+struct FrameResources {
+    camera_buffer: BufferResource,
+    /// Copy of the instances the frame's top level updates read.
+    instance_buffer: BufferResource,
+    /// `instances_version` when `instance_buffer` was last written.
+    instances_version: u64,
+}
+
+/// The objects `reload_shaders` replaces: shader modules, the pipeline and
+/// its layouts, the shader binding table and the descriptor sets. Null
+/// handles are skipped by Vulkan when destroyed.
+struct PipelineObjects {
+    shader_modules: [vk::ShaderModule; 7],
+    rcall_shader_modules: Vec<vk::ShaderModule>,
+    descriptor_bindings: Vec<DescriptorBinding>,
+    descriptor_set_layouts: Vec<vk::DescriptorSetLayout>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    shader_binding_table: Option<ShaderBindingTable>,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+}
+
+impl PipelineObjects {
+    fn destroy(self, device: &ash::Device) {
+        unsafe {
+            // Also frees the descriptor sets
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            drop(self.shader_binding_table);
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            for layout in self.descriptor_set_layouts {
+                device.destroy_descriptor_set_layout(layout, None);
+            }
+            for module in self.shader_modules.iter().chain(&self.rcall_shader_modules) {
+                device.destroy_shader_module(*module, None);
+            }
+        }
+    }
+}
+
+/// A raygen, miss or callable group running the stage at `shader`.
+fn general_shader_group(shader: u32) -> vk::RayTracingShaderGroupCreateInfoNV {
+    vk::RayTracingShaderGroupCreateInfoNV::builder()
+        .ty(vk::RayTracingShaderGroupTypeNV::GENERAL)
+        .general_shader(shader)
+        .closest_hit_shader(vk::SHADER_UNUSED_NV)
+        .any_hit_shader(vk::SHADER_UNUSED_NV)
+        .intersection_shader(vk::SHADER_UNUSED_NV)
+        .build()
+}
+
+fn color_subresource_range() -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
@@ -0,0 +1,732 @@
+//! Device bootstrap: instance, device and queue, optionally with a window and
+//! swapchain, plus the per-frame submission helper.
+
+#[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
+use ash::extensions::khr::XlibSurface;
+use ash::extensions::{
+    ext::DebugReport,
+    khr::{Surface, Swapchain},
+};
+
+use ash::extensions::nv::RayTracing;
+
+#[cfg(target_os = "windows")]
+use ash::extensions::khr::Win32Surface;
+
+use crate::allocator::{MemoryAllocator, DEFAULT_BLOCK_SIZE};
+use crate::error::{Error, Result};
+use ash::version::{DeviceV1_0, EntryV1_0, InstanceV1_0, InstanceV1_1};
+use ash::{vk, Device, Entry, Instance};
+use std::cell::{Cell, RefCell};
+use std::default::Default;
+use std::ffi::{CStr, CString};
+use std::ops::Drop;
+use std::os::raw::{c_char, c_void};
+
+/// Window, surface and swapchain state. Only present when `Base` was created
+/// with a window.
+///
+/// The swapchain, its resolution and images are replaced by
+/// `Base::recreate_swapchain` when the window is resized.
+pub struct Presentation {
+    pub window: winit::Window,
+    pub events_loop: RefCell<winit::EventsLoop>,
+    pub surface_loader: Surface,
+    pub swapchain_loader: Swapchain,
+
+    pub surface: vk::SurfaceKHR,
+    pub surface_resolution: Cell<vk::Extent2D>,
+
+    pub swapchain: Cell<vk::SwapchainKHR>,
+    pub present_images: RefCell<Vec<vk::Image>>,
+}
+
+/// Owns the Vulkan instance, logical device, queue and the command pool
+/// shared by everything built on top of it, plus the window and swapchain
+/// unless created headless.
+pub struct Base {
+    pub entry: Entry,
+    pub instance: Instance,
+    pub device: Device,
+    pub debug_report_loader: DebugReport,
+    pub debug_call_back: vk::DebugReportCallbackEXT,
+
+    pub pdevice: vk::PhysicalDevice,
+    pub device_memory_properties: vk::PhysicalDeviceMemoryProperties,
+    /// Sub-allocates the memory of every buffer, image and acceleration
+    /// structure built on this base.
+    pub allocator: MemoryAllocator,
+    pub queue_family_index: u32,
+    /// Queue used for all submissions, and for presentation when windowed.
+    pub present_queue: vk::Queue,
+
+    /// Format of the swapchain images, or of the offscreen target when
+    /// headless.
+    pub surface_format: vk::SurfaceFormatKHR,
+    pub presentation: Option<Presentation>,
+
+    pub pool: vk::CommandPool,
+    pub command_buffer: vk::CommandBuffer,
+
+    /// Size of the window, in physical pixels, or of the offscreen target
+    /// when headless, as created. `RayTracingApp::resize` follows later
+    /// changes.
+    pub window_width: u32,
+    pub window_height: u32,
+}
+
+impl Base {
+    /// Pumps window events, calling `f` once per event with that event
+    /// until Escape is pressed or the window is closed.
+    pub fn render_loop<F: FnMut(&winit::Event)>(&self, mut f: F) {
+        use winit::*;
+        self.presentation()
+            .events_loop
+            .borrow_mut()
+            .run_forever(|event| {
+                f(&event);
+                match event {
+                    Event::WindowEvent { event, .. } => match event {
+                        WindowEvent::KeyboardInput { input, .. } => {
+                            if let Some(VirtualKeyCode::Escape) = input.virtual_keycode {
+                                ControlFlow::Break
+                            } else {
+                                ControlFlow::Continue
+                            }
+                        }
+                        WindowEvent::CloseRequested => winit::ControlFlow::Break,
+                        _ => ControlFlow::Continue,
+                    },
+                    _ => ControlFlow::Continue,
+                }
+            });
+    }
+
+    /// Returns the window and swapchain state.
+    ///
+    /// Panics if `Base` was created with `new_headless`.
+    pub fn presentation(&self) -> &Presentation {
+        self.presentation
+            .as_ref()
+            .expect("Base was created without a window")
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.presentation.is_none()
+    }
+
+    /// Replaces the swapchain and its images with ones matching the
+    /// window's current size, after waiting for the device to go idle. Call
+    /// this after a resize, or when acquiring or presenting reports
+    /// `ERROR_OUT_OF_DATE_KHR` or a suboptimal swapchain.
+    ///
+    /// Returns the new resolution. While the window is minimized this is
+    /// zero-sized and the old swapchain is kept; try again later.
+    pub fn recreate_swapchain(&self) -> Result<vk::Extent2D> {
+        let presentation = self.presentation();
+        unsafe {
+            self.device.device_wait_idle()?;
+
+            let hidpi_factor = presentation.window.get_hidpi_factor();
+            let window_extent = presentation
+                .window
+                .get_inner_size()
+                .map(|size| size.to_physical(hidpi_factor))
+                .map_or(presentation.surface_resolution.get(), |size| vk::Extent2D {
+                    width: size.width as u32,
+                    height: size.height as u32,
+                });
+            let surface_capabilities = presentation
+                .surface_loader
+                .get_physical_device_surface_capabilities(self.pdevice, presentation.surface)?;
+            let surface_resolution = surface_extent(&surface_capabilities, window_extent);
+            if surface_resolution.width == 0 || surface_resolution.height == 0 {
+                return Ok(surface_resolution);
+            }
+
+            let old_swapchain = presentation.swapchain.get();
+            let swapchain = create_swapchain(
+                presentation,
+                self.pdevice,
+                self.surface_format,
+                &surface_capabilities,
+                surface_resolution,
+                old_swapchain,
+            )?;
+            presentation
+                .swapchain_loader
+                .destroy_swapchain(old_swapchain, None);
+            presentation.swapchain.set(swapchain);
+            presentation.surface_resolution.set(surface_resolution);
+            *presentation.present_images.borrow_mut() = presentation
+                .swapchain_loader
+                .get_swapchain_images(swapchain)?;
+            Ok(surface_resolution)
+        }
+    }
+
+    /// Opens a window of the given logical size and creates a ray tracing
+    /// capable device presenting to it.
+    pub fn new(window_width: u32, window_height: u32) -> Result<Self> {
+        unsafe {
+            let events_loop = winit::EventsLoop::new();
+            let logical_dimensions =
+                winit::dpi::LogicalSize::new(f64::from(window_width), f64::from(window_height));
+            let window = winit::WindowBuilder::new()
+                .with_title("Rust Vulkan NV Ray Tracing w/ HLSL")
+                .with_dimensions(logical_dimensions)
+                .with_resizable(true)
+                .build(&events_loop)?;
+            let hidpi_factor: f64 = window.get_hidpi_factor();
+            let physical_dimensions = logical_dimensions.to_physical(hidpi_factor);
+
+            let (entry, instance, debug_report_loader, debug_call_back) =
+                create_instance(&extension_names())?;
+
+            let surface = create_surface(&entry, &instance, &window)?;
+            let surface_loader = Surface::new(&entry, &instance);
+
+            let mut device_extensions = device_extension_names();
+            device_extensions.push(Swapchain::name());
+
+            let (pdevice, queue_family_index) =
+                pick_physical_device(&instance, &device_extensions, |pdevice, index, info| {
+                    info.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+                        && surface_loader
+                            .get_physical_device_surface_support(pdevice, index, surface)
+                })?;
+
+            let device = create_device(&instance, pdevice, queue_family_index, &device_extensions)?;
+            let present_queue = device.get_device_queue(queue_family_index, 0);
+
+            let surface_formats =
+                surface_loader.get_physical_device_surface_formats(pdevice, surface)?;
+            let surface_format = surface_formats
+                .iter()
+                .map(|sfmt| match sfmt.format {
+                    vk::Format::UNDEFINED => vk::SurfaceFormatKHR {
+                        format: vk::Format::B8G8R8_UNORM,
+                        color_space: sfmt.color_space,
+                    },
+                    _ => *sfmt,
+                })
+                .next()
+                .ok_or(Error::Vulkan(vk::Result::ERROR_FORMAT_NOT_SUPPORTED))?;
+            let surface_capabilities =
+                surface_loader.get_physical_device_surface_capabilities(pdevice, surface)?;
+            let surface_resolution = surface_extent(
+                &surface_capabilities,
+                vk::Extent2D {
+                    width: window_width,
+                    height: window_height,
+                },
+            );
+            let swapchain_loader = Swapchain::new(&instance, &device);
+
+            let presentation = Presentation {
+                window,
+                events_loop: RefCell::new(events_loop),
+                surface_loader,
+                swapchain_loader,
+                surface,
+                surface_resolution: Cell::new(surface_resolution),
+                swapchain: Cell::new(vk::SwapchainKHR::null()),
+                present_images: RefCell::new(Vec::new()),
+            };
+            let swapchain = create_swapchain(
+                &presentation,
+                pdevice,
+                surface_format,
+                &surface_capabilities,
+                surface_resolution,
+                vk::SwapchainKHR::null(),
+            )?;
+            presentation.swapchain.set(swapchain);
+
+            let (pool, command_buffer) = create_command_pool(&device, queue_family_index)?;
+
+            *presentation.present_images.borrow_mut() = presentation
+                .swapchain_loader
+                .get_swapchain_images(swapchain)?;
+            let device_memory_properties = instance.get_physical_device_memory_properties(pdevice);
+            let allocator = create_allocator(&instance, pdevice, device_memory_properties);
+            Ok(Base {
+                entry,
+                instance,
+                device,
+                queue_family_index,
+                pdevice,
+                device_memory_properties,
+                allocator,
+                surface_format,
+                present_queue,
+                presentation: Some(presentation),
+                pool,
+                command_buffer,
+                debug_call_back,
+                debug_report_loader,
+                window_width: physical_dimensions.width as u32,
+                window_height: physical_dimensions.height as u32,
+            })
+        }
+    }
+
+    /// Creates a ray tracing capable device without a window, surface or
+    /// swapchain. The app renders into an offscreen target of the given size.
+    pub fn new_headless(width: u32, height: u32) -> Result<Self> {
+        unsafe {
+            let (entry, instance, debug_report_loader, debug_call_back) =
+                create_instance(&headless_extension_names())?;
+
+            let device_extensions = device_extension_names();
+
+            let (pdevice, queue_family_index) =
+                pick_physical_device(&instance, &device_extensions, |_, _, info| {
+                    info.queue_flags
+                        .intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+                })?;
+
+            let device = create_device(&instance, pdevice, queue_family_index, &device_extensions)?;
+            let present_queue = device.get_device_queue(queue_family_index, 0);
+
+            let (pool, command_buffer) = create_command_pool(&device, queue_family_index)?;
+            let device_memory_properties = instance.get_physical_device_memory_properties(pdevice);
+            let allocator = create_allocator(&instance, pdevice, device_memory_properties);
+
+            Ok(Base {
+                entry,
+                instance,
+                device,
+                queue_family_index,
+                pdevice,
+                device_memory_properties,
+                allocator,
+                // Storage image support is mandatory for RGBA8, unlike BGRA8.
+                surface_format: vk::SurfaceFormatKHR {
+                    format: vk::Format::R8G8B8A8_UNORM,
+                    color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+                },
+                present_queue,
+                presentation: None,
+                pool,
+                command_buffer,
+                debug_call_back,
+                debug_report_loader,
+                window_width: width,
+                window_height: height,
+            })
+        }
+    }
+}
+
+impl Drop for Base {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.device.device_wait_idle();
+            self.device.destroy_command_pool(self.pool, None);
+            self.allocator.destroy(&self.device);
+            if let Some(ref presentation) = self.presentation {
+                presentation
+                    .swapchain_loader
+                    .destroy_swapchain(presentation.swapchain.get(), None);
+            }
+            self.device.destroy_device(None);
+            if let Some(ref presentation) = self.presentation {
+                presentation
+                    .surface_loader
+                    .destroy_surface(presentation.surface, None);
+            }
+            self.debug_report_loader
+                .destroy_debug_report_callback(self.debug_call_back, None);
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+unsafe fn create_instance(
+    extension_names_raw: &[*const i8],
+) -> Result<(Entry, Instance, DebugReport, vk::DebugReportCallbackEXT)> {
+    let entry = Entry::new()?;
+    let app_name = CString::new("Rust_VK_RT_HLSL").unwrap();
+
+    let available = entry.enumerate_instance_extension_properties()?;
+    for name in extension_names_raw.iter().map(|name| CStr::from_ptr(*name)) {
+        if !available
+            .iter()
+            .any(|ext| CStr::from_ptr(ext.extension_name.as_ptr()) == name)
+        {
+            return Err(Error::MissingExtension(name.to_string_lossy().into_owned()));
+        }
+    }
+
+    let layer_names = [CString::new("VK_LAYER_LUNARG_standard_validation").unwrap()];
+    let layers_names_raw: Vec<*const i8> = layer_names
+        .iter()
+        .map(|raw_name| raw_name.as_ptr())
+        .collect();
+
+    let appinfo = vk::ApplicationInfo::builder()
+        .application_name(&app_name)
+        .application_version(0)
+        .engine_name(&app_name)
+        .engine_version(0)
+        .api_version(vk_make_version!(1, 1, 0));
+
+    let create_info = vk::InstanceCreateInfo::builder()
+        .application_info(&appinfo)
+        .enabled_layer_names(&layers_names_raw)
+        .enabled_extension_names(extension_names_raw);
+
+    let instance: Instance = entry.create_instance(&create_info, None)?;
+
+    let debug_info = vk::DebugReportCallbackCreateInfoEXT::builder()
+        .flags(
+            vk::DebugReportFlagsEXT::ERROR
+                | vk::DebugReportFlagsEXT::WARNING
+                | vk::DebugReportFlagsEXT::PERFORMANCE_WARNING,
+        )
+        .pfn_callback(Some(vulkan_debug_callback));
+
+    let debug_report_loader = DebugReport::new(&entry, &instance);
+    let debug_call_back = debug_report_loader.create_debug_report_callback(&debug_info, None)?;
+
+    Ok((entry, instance, debug_report_loader, debug_call_back))
+}
+
+/// Returns the first physical device exposing every extension in
+/// `extensions` and a queue family accepted by `queue_filter`.
+///
+/// Fails with `MissingExtension` if devices exist but none of them supports
+/// an extension, and `NoSuitableDevice` otherwise.
+unsafe fn pick_physical_device<F>(
+    instance: &Instance,
+    extensions: &[&CStr],
+    queue_filter: F,
+) -> Result<(vk::PhysicalDevice, u32)>
+where
+    F: Fn(vk::PhysicalDevice, u32, &vk::QueueFamilyProperties) -> bool,
+{
+    let pdevices = instance.enumerate_physical_devices()?;
+    let mut missing_extension = None;
+    for pdevice in pdevices {
+        if let Some(name) = find_missing_extension(instance, pdevice, extensions)? {
+            missing_extension = Some(name);
+            continue;
+        }
+        let queue_family_index = instance
+            .get_physical_device_queue_family_properties(pdevice)
+            .iter()
+            .enumerate()
+            .position(|(index, info)| queue_filter(pdevice, index as u32, info));
+        if let Some(queue_family_index) = queue_family_index {
+            return Ok((pdevice, queue_family_index as u32));
+        }
+    }
+    Err(match missing_extension {
+        Some(name) => Error::MissingExtension(name),
+        None => Error::NoSuitableDevice,
+    })
+}
+
+unsafe fn find_missing_extension(
+    instance: &Instance,
+    pdevice: vk::PhysicalDevice,
+    extensions: &[&CStr],
+) -> Result<Option<String>> {
+    let available = instance.enumerate_device_extension_properties(pdevice)?;
+    Ok(extensions
+        .iter()
+        .find(|name| {
+            !available
+                .iter()
+                .any(|ext| CStr::from_ptr(ext.extension_name.as_ptr()) == **name)
+        })
+        .map(|name| name.to_string_lossy().into_owned()))
+}
+
+unsafe fn create_device(
+    instance: &Instance,
+    pdevice: vk::PhysicalDevice,
+    queue_family_index: u32,
+    extensions: &[&CStr],
+) -> Result<Device> {
+    let device_extension_names_raw: Vec<*const i8> =
+        extensions.iter().map(|name| name.as_ptr()).collect();
+
+    let priorities = [1.0];
+
+    let queue_info = [vk::DeviceQueueCreateInfo::builder()
+        .queue_family_index(queue_family_index)
+        .queue_priorities(&priorities)
+        .build()];
+
+    let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingFeaturesEXT::builder()
+        .descriptor_binding_variable_descriptor_count(true)
+        .runtime_descriptor_array(true)
+        .build();
+
+    let mut scalar_block = vk::PhysicalDeviceScalarBlockLayoutFeaturesEXT::builder()
+        .scalar_block_layout(true)
+        .build();
+
+    let mut features2 = vk::PhysicalDeviceFeatures2::default();
+    instance
+        .fp_v1_1()
+        .get_physical_device_features2(pdevice, &mut features2);
+
+    let device_create_info = vk::DeviceCreateInfo::builder()
+        .queue_create_infos(&queue_info)
+        .enabled_extension_names(&device_extension_names_raw)
+        .enabled_features(&features2.features)
+        .push_next(&mut scalar_block)
+        .push_next(&mut descriptor_indexing)
+        .build();
+
+    Ok(instance.create_device(pdevice, &device_create_info, None)?)
+}
+
+/// The swapchain extent for a surface: its current extent, or
+/// `window_extent` clamped to the supported range when the surface leaves
+/// that to the swapchain.
+fn surface_extent(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    window_extent: vk::Extent2D,
+) -> vk::Extent2D {
+    match capabilities.current_extent.width {
+        u32::MAX => vk::Extent2D {
+            width: window_extent.width.clamp(
+                capabilities.min_image_extent.width,
+                capabilities.max_image_extent.width,
+            ),
+            height: window_extent.height.clamp(
+                capabilities.min_image_extent.height,
+                capabilities.max_image_extent.height,
+            ),
+        },
+        _ => capabilities.current_extent,
+    }
+}
+
+/// Creates a swapchain of `surface_resolution` for the presentation's
+/// surface, handing over from `old_swapchain` if it isn't null.
+unsafe fn create_swapchain(
+    presentation: &Presentation,
+    pdevice: vk::PhysicalDevice,
+    surface_format: vk::SurfaceFormatKHR,
+    surface_capabilities: &vk::SurfaceCapabilitiesKHR,
+    surface_resolution: vk::Extent2D,
+    old_swapchain: vk::SwapchainKHR,
+) -> Result<vk::SwapchainKHR> {
+    let mut desired_image_count = surface_capabilities.min_image_count + 1;
+    if surface_capabilities.max_image_count > 0
+        && desired_image_count > surface_capabilities.max_image_count
+    {
+        desired_image_count = surface_capabilities.max_image_count;
+    }
+    let pre_transform = if surface_capabilities
+        .supported_transforms
+        .contains(vk::SurfaceTransformFlagsKHR::IDENTITY)
+    {
+        vk::SurfaceTransformFlagsKHR::IDENTITY
+    } else {
+        surface_capabilities.current_transform
+    };
+    let present_modes = presentation
+        .surface_loader
+        .get_physical_device_surface_present_modes(pdevice, presentation.surface)?;
+    let present_mode = present_modes
+        .iter()
+        .cloned()
+        .find(|&mode| mode == vk::PresentModeKHR::MAILBOX)
+        .unwrap_or(vk::PresentModeKHR::FIFO);
+
+    let swapchain_create_info = vk::SwapchainCreateInfoKHR::builder()
+        .surface(presentation.surface)
+        .min_image_count(desired_image_count)
+        .image_color_space(surface_format.color_space)
+        .image_format(surface_format.format)
+        .image_extent(surface_resolution)
+        .image_usage(vk::ImageUsageFlags::TRANSFER_DST)
+        .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .pre_transform(pre_transform)
+        .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+        .present_mode(present_mode)
+        .clipped(true)
+        .image_array_layers(1)
+        .old_swapchain(old_swapchain);
+
+    Ok(presentation
+        .swapchain_loader
+        .create_swapchain(&swapchain_create_info, None)?)
+}
+
+unsafe fn create_command_pool(
+    device: &Device,
+    queue_family_index: u32,
+) -> Result<(vk::CommandPool, vk::CommandBuffer)> {
+    let pool_create_info = vk::CommandPoolCreateInfo::builder()
+        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+        .queue_family_index(queue_family_index);
+
+    let pool = device.create_command_pool(&pool_create_info, None)?;
+
+    let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_buffer_count(1)
+        .command_pool(pool)
+        .level(vk::CommandBufferLevel::PRIMARY);
+
+    let command_buffer = device.allocate_command_buffers(&command_buffer_allocate_info)?[0];
+
+    Ok((pool, command_buffer))
+}
+
+unsafe fn create_allocator(
+    instance: &Instance,
+    pdevice: vk::PhysicalDevice,
+    device_memory_properties: vk::PhysicalDeviceMemoryProperties,
+) -> MemoryAllocator {
+    let limits = instance.get_physical_device_properties(pdevice).limits;
+    MemoryAllocator::new(
+        device_memory_properties,
+        limits.buffer_image_granularity,
+        DEFAULT_BLOCK_SIZE,
+    )
+}
+
+/// Records `f` into `command_buffer`, submits it to `submit_queue` and blocks
+/// until the GPU has finished executing it.
+pub fn record_submit_commandbuffer<D: DeviceV1_0, F: FnOnce(&D, vk::CommandBuffer)>(
+    device: &D,
+    command_buffer: vk::CommandBuffer,
+    submit_queue: vk::Queue,
+    wait_mask: &[vk::PipelineStageFlags],
+    wait_semaphores: &[vk::Semaphore],
+    signal_semaphores: &[vk::Semaphore],
+    f: F,
+) -> Result<()> {
+    unsafe {
+        device.reset_command_buffer(
+            command_buffer,
+            vk::CommandBufferResetFlags::RELEASE_RESOURCES,
+        )?;
+
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        device.begin_command_buffer(command_buffer, &command_buffer_begin_info)?;
+        f(device, command_buffer);
+        device.end_command_buffer(command_buffer)?;
+
+        let submit_fence = device.create_fence(&vk::FenceCreateInfo::default(), None)?;
+
+        let command_buffers = vec![command_buffer];
+
+        let submit_info = vk::SubmitInfo::builder()
+            .wait_semaphores(wait_semaphores)
+            .wait_dst_stage_mask(wait_mask)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(signal_semaphores);
+
+        let result = device
+            .queue_submit(submit_queue, &[submit_info.build()], submit_fence)
+            .and_then(|_| device.wait_for_fences(&[submit_fence], true, u64::MAX));
+        device.destroy_fence(submit_fence, None);
+        Ok(result?)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
+unsafe fn create_surface<E: EntryV1_0, I: InstanceV1_0>(
+    entry: &E,
+    instance: &I,
+    window: &winit::Window,
+) -> Result<vk::SurfaceKHR> {
+    use winit::os::unix::WindowExt;
+    let x11_display = window
+        .get_xlib_display()
+        .ok_or(vk::Result::ERROR_INITIALIZATION_FAILED)?;
+    let x11_window = window
+        .get_xlib_window()
+        .ok_or(vk::Result::ERROR_INITIALIZATION_FAILED)?;
+    let x11_create_info = vk::XlibSurfaceCreateInfoKHR::builder()
+        .window(x11_window)
+        .dpy(x11_display as *mut vk::Display);
+
+    let xlib_surface_loader = XlibSurface::new(entry, instance);
+    Ok(xlib_surface_loader.create_xlib_surface(&x11_create_info, None)?)
+}
+
+#[cfg(target_os = "windows")]
+unsafe fn create_surface<E: EntryV1_0, I: InstanceV1_0>(
+    entry: &E,
+    instance: &I,
+    window: &winit::Window,
+) -> Result<vk::SurfaceKHR> {
+    use std::ptr;
+    use winapi::shared::windef::HWND;
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winit::os::windows::WindowExt;
+
+    let hwnd = window.get_hwnd() as HWND;
+    let hinstance = GetModuleHandleW(ptr::null()) as *const c_void;
+    let win32_create_info = vk::Win32SurfaceCreateInfoKHR {
+        s_type: vk::StructureType::WIN32_SURFACE_CREATE_INFO_KHR,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        hinstance,
+        hwnd: hwnd as *const c_void,
+    };
+    let win32_surface_loader = Win32Surface::new(entry, instance);
+    Ok(win32_surface_loader.create_win32_surface(&win32_create_info, None)?)
+}
+
+#[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
+fn extension_names() -> Vec<*const i8> {
+    vec![
+        Surface::name().as_ptr(),
+        XlibSurface::name().as_ptr(),
+        DebugReport::name().as_ptr(),
+        vk::KhrGetPhysicalDeviceProperties2Fn::name().as_ptr(),
+    ]
+}
+
+#[cfg(windows)]
+fn extension_names() -> Vec<*const i8> {
+    vec![
+        Surface::name().as_ptr(),
+        Win32Surface::name().as_ptr(),
+        DebugReport::name().as_ptr(),
+        vk::KhrGetPhysicalDeviceProperties2Fn::name().as_ptr(),
+    ]
+}
+
+fn headless_extension_names() -> Vec<*const i8> {
+    vec![
+        DebugReport::name().as_ptr(),
+        vk::KhrGetPhysicalDeviceProperties2Fn::name().as_ptr(),
+    ]
+}
+
+/// Device extensions required for ray tracing, independent of presentation.
+fn device_extension_names() -> Vec<&'static CStr> {
+    vec![
+        RayTracing::name(),
+        vk::ExtDescriptorIndexingFn::name(),
+        vk::ExtScalarBlockLayoutFn::name(),
+        vk::KhrGetMemoryRequirements2Fn::name(),
+    ]
+}
+
+unsafe extern "system" fn vulkan_debug_callback(
+    _: vk::DebugReportFlagsEXT,
+    _: vk::DebugReportObjectTypeEXT,
+    _: u64,
+    _: usize,
+    _: i32,
+    _: *const c_char,
+    p_message: *const c_char,
+    _: *mut c_void,
+) -> u32 {
+    println!("{:?}", CStr::from_ptr(p_message));
+    vk::FALSE
+}
@@ -0,0 +1,395 @@
+//! A fly-through pinhole camera, its matrices for the ray generation
+//! shader and WASD plus mouse-look controls.
+//!
+//! Camera space is left-handed like the scene: `+x` right, `+y` up and
+//! `+z` forward. Matrices are column-major, `m[column][row]`, matching
+//! `mat4` in GLSL and `float4x4` in HLSL constant buffers.
+
+use winit::{DeviceEvent, ElementState, Event, MouseButton, VirtualKeyCode, WindowEvent};
+
+pub type Mat4 = [[f32; 4]; 4];
+
+/// Matrices read by the ray generation shaders from the uniform buffer at
+/// binding 3. Rays start at `view_inverse * (0, 0, 0, 1)` and point along
+/// `view_inverse * normalize(projection_inverse * (ndc.x, ndc.y, 1, 1))`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraUniform {
+    pub view_inverse: Mat4,
+    pub projection_inverse: Mat4,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position: [f32; 3],
+    /// Radians about `+y`; zero looks along `+z`, positive turns right.
+    pub yaw: f32,
+    /// Radians above the horizon.
+    pub pitch: f32,
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Camera {
+    /// Two units in front of the origin, looking at it with a 90 degree
+    /// field of view, as the ray generation shaders used to hardcode.
+    fn default() -> Self {
+        Camera {
+            position: [0.0, 0.0, -2.0],
+            yaw: 0.0,
+            pitch: 0.0,
+            fov_y: std::f32::consts::FRAC_PI_2,
+            near: 0.001,
+            far: 1000.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn forward(&self) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        [sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch]
+    }
+
+    pub fn right(&self) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        [cos_yaw, 0.0, -sin_yaw]
+    }
+
+    pub fn up(&self) -> [f32; 3] {
+        cross(self.forward(), self.right())
+    }
+
+    /// Camera to world transform.
+    pub fn view_inverse(&self) -> Mat4 {
+        let [right, up, forward] = [self.right(), self.up(), self.forward()];
+        let p = self.position;
+        [
+            [right[0], right[1], right[2], 0.0],
+            [up[0], up[1], up[2], 0.0],
+            [forward[0], forward[1], forward[2], 0.0],
+            [p[0], p[1], p[2], 1.0],
+        ]
+    }
+
+    /// World to camera transform.
+    pub fn view(&self) -> Mat4 {
+        let [right, up, forward] = [self.right(), self.up(), self.forward()];
+        let p = self.position;
+        from_rows([
+            [right[0], right[1], right[2], -dot(right, p)],
+            [up[0], up[1], up[2], -dot(up, p)],
+            [forward[0], forward[1], forward[2], -dot(forward, p)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Perspective projection to Vulkan clip space, with `y` pointing down
+    /// and depth from 0 at `near` to 1 at `far`.
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        let (sx, sy, a, b) = self.projection_terms(aspect);
+        from_rows([
+            [sx, 0.0, 0.0, 0.0],
+            [0.0, sy, 0.0, 0.0],
+            [0.0, 0.0, a, b],
+            [0.0, 0.0, 1.0, 0.0],
+        ])
+    }
+
+    pub fn projection_inverse(&self, aspect: f32) -> Mat4 {
+        let (sx, sy, a, b) = self.projection_terms(aspect);
+        from_rows([
+            [1.0 / sx, 0.0, 0.0, 0.0],
+            [0.0, 1.0 / sy, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+            [0.0, 0.0, 1.0 / b, -a / b],
+        ])
+    }
+
+    /// The shader constants for an image `aspect` times wider than tall.
+    pub fn uniform(&self, aspect: f32) -> CameraUniform {
+        CameraUniform {
+            view_inverse: self.view_inverse(),
+            projection_inverse: self.projection_inverse(aspect),
+        }
+    }
+
+    fn projection_terms(&self, aspect: f32) -> (f32, f32, f32, f32) {
+        let tan_half_fov = (self.fov_y * 0.5).tan();
+        let depth = self.far / (self.far - self.near);
+        (
+            1.0 / (aspect * tan_half_fov),
+            -1.0 / tan_half_fov,
+            depth,
+            -self.near * depth,
+        )
+    }
+}
+
+/// Flies a `Camera` from window events: W/S move forward and back, A/D
+/// strafe, Q/E move down and up, and dragging with the right mouse button
+/// held looks around.
+#[derive(Clone, Debug)]
+pub struct CameraController {
+    /// Units per second.
+    pub speed: f32,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: f32,
+    /// Held movement keys: forward, back, left, right, down, up.
+    held: [bool; 6],
+    looking: bool,
+    /// Mouse movement since the last `update`.
+    look_delta: (f32, f32),
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        CameraController {
+            speed: 2.0,
+            sensitivity: 0.003,
+            held: [false; 6],
+            looking: false,
+            look_delta: (0.0, 0.0),
+        }
+    }
+}
+
+impl CameraController {
+    pub fn new() -> Self {
+        CameraController::default()
+    }
+
+    /// Records key, button and mouse motion events for the next `update`.
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput { input, .. } => {
+                    let pressed = input.state == ElementState::Pressed;
+                    let key = match input.virtual_keycode {
+                        Some(VirtualKeyCode::W) => 0,
+                        Some(VirtualKeyCode::S) => 1,
+                        Some(VirtualKeyCode::A) => 2,
+                        Some(VirtualKeyCode::D) => 3,
+                        Some(VirtualKeyCode::Q) => 4,
+                        Some(VirtualKeyCode::E) => 5,
+                        _ => return,
+                    };
+                    self.held[key] = pressed;
+                }
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Right,
+                    ..
+                } => {
+                    self.looking = *state == ElementState::Pressed;
+                }
+                WindowEvent::Focused(false) => {
+                    self.held = [false; 6];
+                    self.looking = false;
+                }
+                _ => {}
+            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } if self.looking => {
+                self.look_delta.0 += delta.0 as f32;
+                self.look_delta.1 += delta.1 as f32;
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies the mouse movement and `seconds` of movement with the keys
+    /// currently held.
+    pub fn update(&mut self, camera: &mut Camera, seconds: f32) {
+        let (dx, dy) = std::mem::replace(&mut self.look_delta, (0.0, 0.0));
+        camera.yaw += dx * self.sensitivity;
+        let max_pitch = std::f32::consts::FRAC_PI_2 - 0.01;
+        camera.pitch = (camera.pitch - dy * self.sensitivity).clamp(-max_pitch, max_pitch);
+
+        let axis = |positive: usize, negative: usize| {
+            (self.held[positive] as i32 - self.held[negative] as i32) as f32
+        };
+        let [forward, right] = [camera.forward(), camera.right()];
+        let (along, across, vertical) = (axis(0, 1), axis(3, 2), axis(5, 4));
+        let distance = self.speed * seconds;
+        for (i, position) in camera.position.iter_mut().enumerate() {
+            let up = if i == 1 { 1.0 } else { 0.0 };
+            *position += distance * (along * forward[i] + across * right[i] + vertical * up);
+        }
+    }
+}
+
+fn from_rows(rows: Mat4) -> Mat4 {
+    let mut m = [[0.0; 4]; 4];
+    for (row, values) in rows.iter().enumerate() {
+        for (column, &value) in values.iter().enumerate() {
+            m[column][row] = value;
+        }
+    }
+    m
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multiply(a: &Mat4, b: &Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (column, b_column) in b.iter().enumerate() {
+            for row in 0..4 {
+                m[column][row] = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+            }
+        }
+        m
+    }
+
+    fn transform(m: &Mat4, v: [f32; 4]) -> [f32; 4] {
+        let mut out = [0.0; 4];
+        for (row, value) in out.iter_mut().enumerate() {
+            *value = (0..4).map(|k| m[k][row] * v[k]).sum();
+        }
+        out
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    fn assert_identity(m: &Mat4) {
+        for (column, values) in m.iter().enumerate() {
+            let mut expected = [0.0; 4];
+            expected[column] = 1.0;
+            assert_close(values, &expected);
+        }
+    }
+
+    fn tilted_camera() -> Camera {
+        Camera {
+            position: [1.0, -2.0, 3.5],
+            yaw: 0.7,
+            pitch: -0.3,
+            fov_y: 1.1,
+            ..Camera::default()
+        }
+    }
+
+    #[test]
+    fn inverses_undo_their_matrices() {
+        let camera = tilted_camera();
+        assert_identity(&multiply(&camera.view(), &camera.view_inverse()));
+        assert_identity(&multiply(&camera.view_inverse(), &camera.view()));
+        assert_identity(&multiply(
+            &camera.projection(1.5),
+            &camera.projection_inverse(1.5),
+        ));
+        assert_identity(&multiply(
+            &camera.projection_inverse(1.5),
+            &camera.projection(1.5),
+        ));
+    }
+
+    #[test]
+    fn default_camera_matches_the_old_hardcoded_rays() {
+        let camera = Camera::default();
+        let uniform = camera.uniform(2.0);
+        let origin = transform(&uniform.view_inverse, [0.0, 0.0, 0.0, 1.0]);
+        assert_close(&origin, &[0.0, 0.0, -2.0, 1.0]);
+
+        // The old shaders traced normalize(d.x * aspect, -d.y, 1)
+        let (x, y) = (0.5, 0.25);
+        let target = transform(&uniform.projection_inverse, [x, y, 1.0, 1.0]);
+        let target = [
+            target[0] / target[3],
+            target[1] / target[3],
+            target[2] / target[3],
+        ];
+        let length = dot(target, target).sqrt();
+        let direction = transform(
+            &uniform.view_inverse,
+            [
+                target[0] / length,
+                target[1] / length,
+                target[2] / length,
+                0.0,
+            ],
+        );
+        let expected = [x * 2.0, -y, 1.0];
+        let expected_length = dot(expected, expected).sqrt();
+        assert_close(
+            &direction,
+            &[
+                expected[0] / expected_length,
+                expected[1] / expected_length,
+                expected[2] / expected_length,
+                0.0,
+            ],
+        );
+    }
+
+    #[test]
+    fn projection_maps_near_and_far_to_depth_range() {
+        let camera = tilted_camera();
+        let projection = camera.projection(1.0);
+        let near = transform(&projection, [0.0, 0.0, camera.near, 1.0]);
+        let far = transform(&projection, [0.0, 0.0, camera.far, 1.0]);
+        assert_close(&[near[2] / near[3], far[2] / far[3]], &[0.0, 1.0]);
+
+        let top = (camera.fov_y * 0.5).tan();
+        let corner = transform(&projection, [top, top, 1.0, 1.0]);
+        assert_close(
+            &[corner[0] / corner[3], corner[1] / corner[3]],
+            &[1.0, -1.0],
+        );
+    }
+
+    #[test]
+    fn yaw_turns_right_and_pitch_looks_up() {
+        let mut camera = Camera {
+            yaw: std::f32::consts::FRAC_PI_2,
+            ..Camera::default()
+        };
+        assert_close(&camera.forward(), &[1.0, 0.0, 0.0]);
+        assert_close(&camera.right(), &[0.0, 0.0, -1.0]);
+
+        camera.yaw = 0.0;
+        camera.pitch = std::f32::consts::FRAC_PI_4;
+        assert!(camera.forward()[1] > 0.0);
+        assert_close(&[dot(camera.up(), camera.forward())], &[0.0]);
+    }
+
+    #[test]
+    fn held_keys_move_along_the_view() {
+        let mut camera = Camera::default();
+        let mut controller = CameraController::new();
+        controller.held[0] = true;
+        controller.held[3] = true;
+        controller.update(&mut camera, 0.5);
+        assert_close(&camera.position, &[1.0, 0.0, -1.0]);
+
+        controller.held = [false; 6];
+        controller.looking = true;
+        controller.look_delta = (0.0, 10_000.0);
+        controller.update(&mut camera, 0.5);
+        assert!(camera.pitch < 0.0 && camera.pitch > -std::f32::consts::FRAC_PI_2);
+        assert_close(&camera.position, &[1.0, 0.0, -1.0]);
+    }
+}
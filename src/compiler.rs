@@ -0,0 +1,296 @@
+//! In-process compilation of HLSL and GLSL ray tracing shaders to SPIR-V.
+//!
+//! HLSL goes through DXC (`hlsl-compiler` feature, via hassle-rs) and GLSL
+//! through glslang (`glsl-compiler` feature, via shaderc), mirroring
+//! `shaders/compile.sh`. Results are cached on disk under a hash of the
+//! source and options, so unchanged shaders are only compiled once.
+//! `#include`d files and the compiler version are not part of that hash.
+
+use crate::error::{Error, Result};
+use crate::pipeline::read_shader;
+use ash::vk;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Source language of a shader, chosen by file extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderLanguage {
+    Hlsl,
+    Glsl,
+}
+
+impl ShaderLanguage {
+    /// The language of `path` if it ends in `.hlsl` or `.glsl`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("hlsl") => Some(ShaderLanguage::Hlsl),
+            Some("glsl") => Some(ShaderLanguage::Glsl),
+            _ => None,
+        }
+    }
+}
+
+/// Options passed to the compiler for every shader.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderCompileOptions {
+    /// Entry point name. Ignored by DXC for `lib_*` profiles, where every
+    /// `[shader(...)]` function is exported.
+    pub entry_point: String,
+    /// DXC target profile for HLSL, `lib_6_3` for ray tracing libraries.
+    pub target_profile: String,
+    /// Preprocessor defines, with optional values.
+    pub defines: Vec<(String, Option<String>)>,
+    /// Directory for compiled modules, or `None` to always compile.
+    /// Entries are keyed by the source file and these options only, so
+    /// clear the directory after editing an `#include`d file or upgrading
+    /// DXC or shaderc.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Default for ShaderCompileOptions {
+    fn default() -> Self {
+        ShaderCompileOptions {
+            entry_point: "main".to_owned(),
+            target_profile: "lib_6_3".to_owned(),
+            defines: Vec::new(),
+            cache_dir: Some(std::env::temp_dir().join("ash-nv-rt-shader-cache")),
+        }
+    }
+}
+
+/// Compiles the shader source at `path`, or reads it back from the cache.
+/// Failing to write the cache is reported on stderr but not returned, as
+/// the compiled code is still usable.
+///
+/// GLSL sources need their stage in the file name, following the
+/// `name[.variant].stage.glsl` convention of `shaders/`, e.g.
+/// `triangle.rchit.glsl`.
+pub fn compile_shader(path: &Path, options: &ShaderCompileOptions) -> Result<Vec<u32>> {
+    let compile_error = |message: String| Error::ShaderCompile {
+        path: path.to_owned(),
+        message,
+    };
+    let language = ShaderLanguage::from_path(path)
+        .ok_or_else(|| compile_error("Not an .hlsl or .glsl file".to_owned()))?;
+    let stage = match language {
+        ShaderLanguage::Hlsl => None,
+        ShaderLanguage::Glsl => Some(
+            stage_from_path(path)
+                .ok_or_else(|| compile_error("No shader stage in the file name".to_owned()))?,
+        ),
+    };
+    let source = fs::read_to_string(path).map_err(|source| Error::ShaderLoad {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    let cache_path = options.cache_dir.as_ref().map(|cache_dir| {
+        let key = cache_key(&source, language, stage, options);
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        cache_dir.join(format!("{}.{:016x}.spv", file_name, key))
+    });
+    if let Some(cache_path) = &cache_path {
+        if let Ok(code) = read_shader(cache_path) {
+            return Ok(code);
+        }
+    }
+
+    let name = path.to_string_lossy();
+    let code = match language {
+        ShaderLanguage::Hlsl => compile_hlsl(&source, &name, options),
+        ShaderLanguage::Glsl => compile_glsl(&source, &name, stage.unwrap(), options),
+    }
+    .map_err(compile_error)?;
+
+    if let Some(cache_path) = &cache_path {
+        if let Err(err) = write_cache(cache_path, &code) {
+            eprintln!(
+                "Could not cache {:?} in {:?}: {}",
+                path.file_name().unwrap_or_default(),
+                cache_path.parent().unwrap_or(cache_path),
+                err
+            );
+        }
+    }
+    Ok(code)
+}
+
+fn write_cache(cache_path: &Path, code: &[u32]) -> std::io::Result<()> {
+    if let Some(cache_dir) = cache_path.parent() {
+        fs::create_dir_all(cache_dir)?;
+    }
+    let bytes: Vec<u8> = code.iter().flat_map(|word| word.to_le_bytes()).collect();
+    fs::write(cache_path, bytes)
+}
+
+/// The stage named by the second to last extension of `path`.
+fn stage_from_path(path: &Path) -> Option<vk::ShaderStageFlags> {
+    let stem = Path::new(path.file_stem()?);
+    Some(match stem.extension()?.to_str()? {
+        "rgen" => vk::ShaderStageFlags::RAYGEN_NV,
+        "rint" => vk::ShaderStageFlags::INTERSECTION_NV,
+        "rahit" => vk::ShaderStageFlags::ANY_HIT_NV,
+        "rchit" => vk::ShaderStageFlags::CLOSEST_HIT_NV,
+        "rmiss" => vk::ShaderStageFlags::MISS_NV,
+        "rcall" => vk::ShaderStageFlags::CALLABLE_NV,
+        "comp" => vk::ShaderStageFlags::COMPUTE,
+        _ => return None,
+    })
+}
+
+/// FNV-1a over the source and options. Stable across runs and toolchains,
+/// unlike `DefaultHasher`. Included files and the compiler version are not
+/// hashed, see `ShaderCompileOptions::cache_dir`.
+fn cache_key(
+    source: &str,
+    language: ShaderLanguage,
+    stage: Option<vk::ShaderStageFlags>,
+    options: &ShaderCompileOptions,
+) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut write = |bytes: &[u8]| {
+        for &byte in bytes.iter().chain(&[0xff]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    write(&[language as u8]);
+    write(&stage.map_or(0, |stage| stage.as_raw()).to_le_bytes());
+    write(options.entry_point.as_bytes());
+    write(options.target_profile.as_bytes());
+    for (name, value) in &options.defines {
+        write(name.as_bytes());
+        write(value.as_deref().unwrap_or("").as_bytes());
+    }
+    write(source.as_bytes());
+    hash
+}
+
+#[cfg(feature = "hlsl-compiler")]
+fn compile_hlsl(
+    source: &str,
+    name: &str,
+    options: &ShaderCompileOptions,
+) -> std::result::Result<Vec<u32>, String> {
+    let defines: Vec<(&str, Option<&str>)> = options
+        .defines
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_deref()))
+        .collect();
+    let bytes = hassle_rs::compile_hlsl(
+        name,
+        source,
+        &options.entry_point,
+        &options.target_profile,
+        &["-spirv"],
+        &defines,
+    )
+    .map_err(|err| err.to_string())?;
+    ash::util::read_spv(&mut std::io::Cursor::new(bytes)).map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "hlsl-compiler"))]
+fn compile_hlsl(
+    _source: &str,
+    _name: &str,
+    _options: &ShaderCompileOptions,
+) -> std::result::Result<Vec<u32>, String> {
+    Err("Built without the `hlsl-compiler` feature".to_owned())
+}
+
+#[cfg(feature = "glsl-compiler")]
+fn compile_glsl(
+    source: &str,
+    name: &str,
+    stage: vk::ShaderStageFlags,
+    options: &ShaderCompileOptions,
+) -> std::result::Result<Vec<u32>, String> {
+    use shaderc::ShaderKind;
+
+    let kind = match stage {
+        vk::ShaderStageFlags::RAYGEN_NV => ShaderKind::RayGeneration,
+        vk::ShaderStageFlags::INTERSECTION_NV => ShaderKind::Intersection,
+        vk::ShaderStageFlags::ANY_HIT_NV => ShaderKind::AnyHit,
+        vk::ShaderStageFlags::CLOSEST_HIT_NV => ShaderKind::ClosestHit,
+        vk::ShaderStageFlags::MISS_NV => ShaderKind::Miss,
+        vk::ShaderStageFlags::CALLABLE_NV => ShaderKind::Callable,
+        _ => ShaderKind::Compute,
+    };
+    let mut compiler =
+        shaderc::Compiler::new().ok_or_else(|| "Could not create shaderc compiler".to_owned())?;
+    let mut compile_options = shaderc::CompileOptions::new()
+        .ok_or_else(|| "Could not create shaderc options".to_owned())?;
+    for (name, value) in &options.defines {
+        compile_options.add_macro_definition(name, value.as_deref());
+    }
+    let artifact = compiler
+        .compile_into_spirv(
+            source,
+            kind,
+            name,
+            &options.entry_point,
+            Some(&compile_options),
+        )
+        .map_err(|err| err.to_string())?;
+    Ok(artifact.as_binary().to_vec())
+}
+
+#[cfg(not(feature = "glsl-compiler"))]
+fn compile_glsl(
+    _source: &str,
+    _name: &str,
+    _stage: vk::ShaderStageFlags,
+    _options: &ShaderCompileOptions,
+) -> std::result::Result<Vec<u32>, String> {
+    Err("Built without the `glsl-compiler` feature".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_follow_the_shader_file_names() {
+        let stage = |name| stage_from_path(Path::new(name));
+        assert_eq!(
+            stage("triangle.rgen.glsl"),
+            Some(vk::ShaderStageFlags::RAYGEN_NV)
+        );
+        assert_eq!(
+            stage("shaders/triangle.bindless.rchit.glsl"),
+            Some(vk::ShaderStageFlags::CLOSEST_HIT_NV)
+        );
+        assert_eq!(stage("wave.comp.glsl"), Some(vk::ShaderStageFlags::COMPUTE));
+        assert_eq!(stage("triangle.glsl"), None);
+    }
+
+    #[test]
+    fn cache_key_changes_with_source_and_options() {
+        let options = ShaderCompileOptions::default();
+        let key = |source, options: &ShaderCompileOptions| {
+            cache_key(source, ShaderLanguage::Hlsl, None, options)
+        };
+        let base = key("void main() {}", &options);
+        assert_eq!(base, key("void main() {}", &options));
+        assert_ne!(base, key("void main() { }", &options));
+
+        let mut defined = options.clone();
+        defined.defines.push(("SHADOWS".to_owned(), None));
+        assert_ne!(base, key("void main() {}", &defined));
+
+        let mut profile = options;
+        profile.target_profile = "lib_6_5".to_owned();
+        assert_ne!(base, key("void main() {}", &profile));
+    }
+
+    #[test]
+    fn glsl_without_a_stage_is_rejected() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("shaders/triangle.glsl");
+        match compile_shader(&path, &ShaderCompileOptions::default()) {
+            Err(Error::ShaderCompile { message, .. }) => {
+                assert_eq!(message, "No shader stage in the file name");
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
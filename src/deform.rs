@@ -0,0 +1,240 @@
+//! Compute pre-pass that rewrites the vertices of deformable meshes before
+//! their bottom-level acceleration structures are refitted.
+
+use crate::base::Base;
+use crate::error::Result;
+use crate::mesh::MeshBuffers;
+use crate::pipeline::load_shader_module;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::ffi::CStr;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Threads per workgroup along x; deformation shaders must declare
+/// `local_size_x` to match.
+pub const DEFORM_WORKGROUP_SIZE: u32 = 64;
+
+/// Push constants passed to deformation shaders.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DeformParams {
+    /// Seconds of animation time.
+    pub time: f32,
+    pub vertex_count: u32,
+}
+
+/// A compute pipeline run once per deformable mesh.
+///
+/// The shader reads rest positions from a storage buffer at binding 0 and
+/// writes deformed positions to binding 1, both as tightly packed
+/// `float[3 * vertex_count]`, one invocation per vertex.
+pub struct VertexDeformer {
+    shader_module: vk::ShaderModule,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    descriptor_pool: vk::DescriptorPool,
+    /// One set per deformable mesh, with its vertex count.
+    descriptor_sets: Vec<(vk::DescriptorSet, u32)>,
+    base: Rc<Base>,
+}
+
+impl VertexDeformer {
+    /// Loads the compute shader at `shader_path` and binds the deformable
+    /// entries of `meshes`. Those must outlive the deformer.
+    pub fn new(shader_path: &Path, meshes: &[&MeshBuffers], base: Rc<Base>) -> Result<Self> {
+        let meshes: Vec<&MeshBuffers> = meshes
+            .iter()
+            .copied()
+            .filter(|mesh| mesh.is_deformable())
+            .collect();
+
+        // Owning the handles right away lets Drop clean up on failure below.
+        let mut deformer = VertexDeformer {
+            shader_module: vk::ShaderModule::null(),
+            descriptor_set_layout: vk::DescriptorSetLayout::null(),
+            pipeline_layout: vk::PipelineLayout::null(),
+            pipeline: vk::Pipeline::null(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            descriptor_sets: Vec::new(),
+            base,
+        };
+        let device = &deformer.base.device;
+
+        unsafe {
+            deformer.shader_module = load_shader_module(device, shader_path)?;
+
+            let storage_binding = |binding| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .descriptor_count(1)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                    .binding(binding)
+                    .build()
+            };
+            deformer.descriptor_set_layout = device.create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::builder()
+                    .bindings(&[storage_binding(0), storage_binding(1)]),
+                None,
+            )?;
+
+            let push_constant_ranges = [vk::PushConstantRange::builder()
+                .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                .size(std::mem::size_of::<DeformParams>() as u32)
+                .build()];
+            deformer.pipeline_layout = device.create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::builder()
+                    .set_layouts(&[deformer.descriptor_set_layout])
+                    .push_constant_ranges(&push_constant_ranges),
+                None,
+            )?;
+
+            deformer.pipeline = device
+                .create_compute_pipelines(
+                    vk::PipelineCache::null(),
+                    &[vk::ComputePipelineCreateInfo::builder()
+                        .stage(
+                            vk::PipelineShaderStageCreateInfo::builder()
+                                .stage(vk::ShaderStageFlags::COMPUTE)
+                                .module(deformer.shader_module)
+                                .name(CStr::from_bytes_with_nul(b"main\0").unwrap())
+                                .build(),
+                        )
+                        .layout(deformer.pipeline_layout)
+                        .build()],
+                    None,
+                )
+                .map_err(|(_, err)| err)?[0];
+
+            if meshes.is_empty() {
+                return Ok(deformer);
+            }
+
+            deformer.descriptor_pool = device.create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::builder()
+                    .pool_sizes(&[vk::DescriptorPoolSize {
+                        ty: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: 2 * meshes.len() as u32,
+                    }])
+                    .max_sets(meshes.len() as u32),
+                None,
+            )?;
+
+            let set_layouts = vec![deformer.descriptor_set_layout; meshes.len()];
+            let descriptor_sets = device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(deformer.descriptor_pool)
+                    .set_layouts(&set_layouts),
+            )?;
+
+            for (&descriptor_set, mesh) in descriptor_sets.iter().zip(&meshes) {
+                let rest_info = [vk::DescriptorBufferInfo::builder()
+                    .buffer(mesh.rest_vertex_buffer.as_ref().unwrap().buffer)
+                    .range(vk::WHOLE_SIZE)
+                    .build()];
+                let deformed_info = [vk::DescriptorBufferInfo::builder()
+                    .buffer(mesh.vertex_buffer.buffer)
+                    .range(vk::WHOLE_SIZE)
+                    .build()];
+                device.update_descriptor_sets(
+                    &[
+                        vk::WriteDescriptorSet::builder()
+                            .dst_set(descriptor_set)
+                            .dst_binding(0)
+                            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                            .buffer_info(&rest_info)
+                            .build(),
+                        vk::WriteDescriptorSet::builder()
+                            .dst_set(descriptor_set)
+                            .dst_binding(1)
+                            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                            .buffer_info(&deformed_info)
+                            .build(),
+                    ],
+                    &[],
+                );
+                deformer
+                    .descriptor_sets
+                    .push((descriptor_set, mesh.vertex_count));
+            }
+        }
+
+        Ok(deformer)
+    }
+
+    /// Records the deformation of every bound mesh at `time`, followed by a
+    /// barrier that makes the new vertices visible to acceleration
+    /// structure builds.
+    pub fn record(&self, command_buffer: vk::CommandBuffer, time: f32) {
+        let device = &self.base.device;
+        unsafe {
+            // Earlier builds must be done reading the vertices.
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_NV,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[],
+            );
+
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline,
+            );
+            for &(descriptor_set, vertex_count) in &self.descriptor_sets {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.pipeline_layout,
+                    0,
+                    &[descriptor_set],
+                    &[],
+                );
+                let params = DeformParams { time, vertex_count };
+                device.cmd_push_constants(
+                    command_buffer,
+                    self.pipeline_layout,
+                    vk::ShaderStageFlags::COMPUTE,
+                    0,
+                    std::slice::from_raw_parts(
+                        &params as *const DeformParams as *const u8,
+                        std::mem::size_of::<DeformParams>(),
+                    ),
+                );
+                let group_count = vertex_count.div_ceil(DEFORM_WORKGROUP_SIZE);
+                device.cmd_dispatch(command_buffer, group_count, 1, 1);
+            }
+
+            let memory_barrier = vk::MemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+                .dst_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_READ_NV)
+                .build();
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_NV,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+        }
+    }
+}
+
+impl Drop for VertexDeformer {
+    fn drop(&mut self) {
+        unsafe {
+            let device = &self.base.device;
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            device.destroy_shader_module(self.shader_module, None);
+        }
+    }
+}
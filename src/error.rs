@@ -0,0 +1,166 @@
+//! The crate-wide error type.
+
+use ash::vk;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The Vulkan loader library could not be loaded.
+    Loading(ash::LoadingError),
+    /// The window could not be created.
+    Window(winit::CreationError),
+    /// A required instance or device extension is not available.
+    MissingExtension(String),
+    /// No physical device has the queue family and extensions needed.
+    NoSuitableDevice,
+    /// No memory type satisfies a resource's requirements and property flags.
+    NoSuitableMemoryType,
+    /// Pixels in this format cannot be converted for export.
+    UnsupportedFormat(vk::Format),
+    /// A SPIR-V file could not be opened or read.
+    ShaderLoad {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A shader source could not be compiled, with the compiler's
+    /// diagnostics.
+    ShaderCompile {
+        path: PathBuf,
+        message: String,
+    },
+    /// A SPIR-V module could not be reflected, or two modules disagree on
+    /// a descriptor binding.
+    Reflection(String),
+    /// A shader binding table record, handle plus inline data, is larger
+    /// than the device's `max_shader_group_stride`.
+    ShaderRecordTooLarge {
+        stride: vk::DeviceSize,
+        max: u32,
+    },
+    /// A pipeline traces rays from more nested shader stages than the
+    /// device's `max_recursion_depth` allows.
+    RecursionDepthTooLarge {
+        depth: u32,
+        max: u32,
+    },
+    /// A scene instance references a bottom level or model that does not
+    /// exist.
+    UnknownBottomLevel {
+        index: usize,
+        count: usize,
+    },
+    /// A scene has no instances to build a top-level acceleration structure
+    /// from.
+    EmptyScene,
+    /// An OBJ file could not be parsed.
+    Obj(tobj::LoadError),
+    /// A glTF file or one of its buffers could not be loaded.
+    Gltf(gltf::Error),
+    /// A Vulkan call failed.
+    Vulkan(vk::Result),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Loading(err) => write!(f, "Failed to load Vulkan: {}", err),
+            Error::Window(err) => write!(f, "Failed to create window: {}", err),
+            Error::MissingExtension(name) => write!(f, "Missing Vulkan extension: {}", name),
+            Error::NoSuitableDevice => write!(f, "Couldn't find suitable device"),
+            Error::NoSuitableMemoryType => write!(f, "Unable to find suitable memory type"),
+            Error::UnsupportedFormat(format) => write!(f, "Unsupported image format: {:?}", format),
+            Error::ShaderLoad { path, source } => {
+                write!(f, "Could not load shader {:?}: {}", path, source)
+            }
+            Error::ShaderCompile { path, message } => {
+                write!(f, "Could not compile shader {:?}:\n{}", path, message)
+            }
+            Error::Reflection(message) => write!(f, "Shader reflection failed: {}", message),
+            Error::ShaderRecordTooLarge { stride, max } => write!(
+                f,
+                "Shader record stride {} exceeds the maximum of {}",
+                stride, max
+            ),
+            Error::RecursionDepthTooLarge { depth, max } => write!(
+                f,
+                "Ray recursion depth {} exceeds the maximum of {}",
+                depth, max
+            ),
+            Error::UnknownBottomLevel { index, count } => write!(
+                f,
+                "Instance references bottom level {}, but only {} exist",
+                index, count
+            ),
+            Error::EmptyScene => write!(f, "Scene has no instances"),
+            Error::Obj(err) => write!(f, "Failed to load OBJ: {}", err),
+            Error::Gltf(err) => write!(f, "Failed to load glTF: {}", err),
+            Error::Vulkan(result) => write!(f, "Vulkan error: {}", result),
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Loading(err) => Some(err),
+            Error::Window(err) => Some(err),
+            Error::ShaderLoad { source, .. } => Some(source),
+            Error::Obj(err) => Some(err),
+            Error::Gltf(err) => Some(err),
+            Error::Vulkan(result) => Some(result),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<vk::Result> for Error {
+    fn from(result: vk::Result) -> Self {
+        Error::Vulkan(result)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ash::LoadingError> for Error {
+    fn from(err: ash::LoadingError) -> Self {
+        Error::Loading(err)
+    }
+}
+
+impl From<ash::InstanceError> for Error {
+    fn from(err: ash::InstanceError) -> Self {
+        match err {
+            ash::InstanceError::LoadError(names) => Error::MissingExtension(names.join(", ")),
+            ash::InstanceError::VkError(result) => Error::Vulkan(result),
+        }
+    }
+}
+
+impl From<tobj::LoadError> for Error {
+    fn from(err: tobj::LoadError) -> Self {
+        Error::Obj(err)
+    }
+}
+
+impl From<gltf::Error> for Error {
+    fn from(err: gltf::Error) -> Self {
+        Error::Gltf(err)
+    }
+}
+
+impl From<winit::CreationError> for Error {
+    fn from(err: winit::CreationError) -> Self {
+        Error::Window(err)
+    }
+}
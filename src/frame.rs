@@ -0,0 +1,161 @@
+//! Several frames in flight: per-frame command buffers, fences and
+//! semaphores, so the CPU records one frame while the GPU runs another.
+
+use crate::base::Base;
+use crate::error::Result;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::rc::Rc;
+
+/// Frames the triangle example keeps in flight.
+pub const FRAMES_IN_FLIGHT: usize = 2;
+
+/// The synchronization objects and command buffer of one frame in flight.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameSlot {
+    /// Selects the slot's per-frame resources, such as uniform buffers.
+    pub index: usize,
+    pub command_buffer: vk::CommandBuffer,
+    /// Signaled when the slot's last submission has finished.
+    pub fence: vk::Fence,
+    /// For `acquire_next_image` to signal; the submission waits on it.
+    pub image_available: vk::Semaphore,
+    /// Signaled by the submission, for `queue_present` to wait on.
+    pub render_finished: vk::Semaphore,
+}
+
+/// Cycles through a fixed number of `FrameSlot`s. Waiting for a slot's
+/// fence before reusing it keeps the CPU at most that many frames ahead,
+/// and makes it safe to rewrite the slot's host visible resources.
+pub struct FrameManager {
+    slots: Vec<FrameSlot>,
+    next: usize,
+    base: Rc<Base>,
+}
+
+impl FrameManager {
+    /// Allocates `frame_count` command buffers from the base's pool, with a
+    /// signaled fence and two semaphores each.
+    pub fn new(frame_count: usize, base: Rc<Base>) -> Result<Self> {
+        let mut manager = FrameManager {
+            slots: Vec::new(),
+            next: 0,
+            base,
+        };
+        let device = &manager.base.device;
+        unsafe {
+            let command_buffers = device.allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::builder()
+                    .command_buffer_count(frame_count as u32)
+                    .command_pool(manager.base.pool)
+                    .level(vk::CommandBufferLevel::PRIMARY),
+            )?;
+            // Owning the handles right away lets Drop clean up on failure below.
+            for (index, command_buffer) in command_buffers.into_iter().enumerate() {
+                manager.slots.push(FrameSlot {
+                    index,
+                    command_buffer,
+                    fence: vk::Fence::null(),
+                    image_available: vk::Semaphore::null(),
+                    render_finished: vk::Semaphore::null(),
+                });
+            }
+            for slot in &mut manager.slots {
+                // Signaled, so the first wait on each slot returns at once
+                slot.fence = device.create_fence(
+                    &vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED),
+                    None,
+                )?;
+                slot.image_available =
+                    device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None)?;
+                slot.render_finished =
+                    device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None)?;
+            }
+        }
+        Ok(manager)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Waits until the next slot's previous submission has finished, then
+    /// returns that slot.
+    pub fn next_frame(&mut self) -> Result<FrameSlot> {
+        let slot = self.slots[self.next];
+        self.next = (self.next + 1) % self.slots.len();
+        unsafe {
+            self.base
+                .device
+                .wait_for_fences(&[slot.fence], true, u64::MAX)?;
+        }
+        Ok(slot)
+    }
+
+    /// Records `f` into the slot's command buffer and submits it without
+    /// waiting. The submission waits for `image_available` at `wait_stage`,
+    /// then signals `render_finished` and the fence.
+    pub fn submit<F: FnOnce(vk::CommandBuffer)>(
+        &self,
+        slot: &FrameSlot,
+        wait_stage: vk::PipelineStageFlags,
+        f: F,
+    ) -> Result<()> {
+        let device = &self.base.device;
+        unsafe {
+            device.reset_command_buffer(
+                slot.command_buffer,
+                vk::CommandBufferResetFlags::RELEASE_RESOURCES,
+            )?;
+            device.begin_command_buffer(
+                slot.command_buffer,
+                &vk::CommandBufferBeginInfo::builder()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+            f(slot.command_buffer);
+            device.end_command_buffer(slot.command_buffer)?;
+
+            // Only reset once something will signal the fence again
+            device.reset_fences(&[slot.fence])?;
+
+            let wait_semaphores = [slot.image_available];
+            let wait_stages = [wait_stage];
+            let command_buffers = [slot.command_buffer];
+            let signal_semaphores = [slot.render_finished];
+            let submit_info = vk::SubmitInfo::builder()
+                .wait_semaphores(&wait_semaphores)
+                .wait_dst_stage_mask(&wait_stages)
+                .command_buffers(&command_buffers)
+                .signal_semaphores(&signal_semaphores);
+            device.queue_submit(self.base.present_queue, &[submit_info.build()], slot.fence)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FrameManager {
+    fn drop(&mut self) {
+        unsafe {
+            let device = &self.base.device;
+            let fences: Vec<vk::Fence> = self
+                .slots
+                .iter()
+                .map(|slot| slot.fence)
+                .filter(|&fence| fence != vk::Fence::null())
+                .collect();
+            if !fences.is_empty() {
+                let _ = device.wait_for_fences(&fences, true, u64::MAX);
+            }
+            for slot in &self.slots {
+                device.destroy_fence(slot.fence, None);
+                device.destroy_semaphore(slot.image_available, None);
+                device.destroy_semaphore(slot.render_finished, None);
+            }
+            let command_buffers: Vec<vk::CommandBuffer> =
+                self.slots.iter().map(|slot| slot.command_buffer).collect();
+            if !command_buffers.is_empty() {
+                device.free_command_buffers(self.base.pool, &command_buffers);
+            }
+        }
+    }
+}
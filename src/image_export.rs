@@ -0,0 +1,341 @@
+//! CPU-side pixel conversion and image file encoding for frames read back
+//! from the GPU.
+
+use ash::vk;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Tightly packed RGBA8 pixels in host memory, top row first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl HostImage {
+    /// Converts tightly packed texels of `format` to RGBA8. Returns `None`
+    /// if the format is not supported or `data` is too short.
+    pub fn from_raw(width: u32, height: u32, format: vk::Format, data: &[u8]) -> Option<Self> {
+        let texel_count = width as usize * height as usize;
+        let texel_size = texel_size(format)?;
+        if data.len() < texel_count * texel_size {
+            return None;
+        }
+        let pixels = convert_to_rgba8(format, &data[..texel_count * texel_size])?;
+        Some(HostImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Writes the image to `path`, picking the encoder from the extension
+    /// (`png`, `ppm` or `exr`).
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        let mut writer = BufWriter::new(File::create(path)?);
+        match extension.as_deref() {
+            Some("png") => self.write_png(&mut writer),
+            Some("ppm") => self.write_ppm(&mut writer),
+            Some("exr") => self.write_exr(&mut writer),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported image extension: {:?}", path),
+            )),
+        }?;
+        writer.flush()
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        writer.write_image_data(&self.pixels).map_err(png_error)
+    }
+
+    /// Writes a binary (P6) PPM. Alpha is dropped.
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let rgb: Vec<u8> = self
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| pixel[..3].iter().cloned())
+            .collect();
+        writer.write_all(&rgb)
+    }
+
+    /// Writes an uncompressed scanline OpenEXR with 32-bit float RGBA
+    /// channels, mapping 0..255 to 0.0..1.0. The values keep the 8-bit
+    /// precision and range of the pixels. Fails for an empty image, which
+    /// EXR cannot describe.
+    pub fn write_exr<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "EXR images need at least one pixel",
+            ));
+        }
+
+        // Channels must be listed, and stored, in alphabetical order.
+        const CHANNELS: [(&[u8], usize); 4] = [(b"A", 3), (b"B", 2), (b"G", 1), (b"R", 0)];
+        const PIXEL_TYPE_FLOAT: i32 = 2;
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+        header.extend_from_slice(&2i32.to_le_bytes());
+
+        let mut channels = Vec::new();
+        for (name, _) in CHANNELS.iter() {
+            channels.extend_from_slice(name);
+            channels.push(0);
+            channels.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+            channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear + reserved
+            channels.extend_from_slice(&1i32.to_le_bytes()); // xSampling
+            channels.extend_from_slice(&1i32.to_le_bytes()); // ySampling
+        }
+        channels.push(0);
+
+        let mut window = Vec::new();
+        for value in &[0, 0, self.width as i32 - 1, self.height as i32 - 1] {
+            window.extend_from_slice(&value.to_le_bytes());
+        }
+
+        write_exr_attribute(&mut header, "channels", "chlist", &channels);
+        write_exr_attribute(&mut header, "compression", "compression", &[0]);
+        write_exr_attribute(&mut header, "dataWindow", "box2i", &window);
+        write_exr_attribute(&mut header, "displayWindow", "box2i", &window);
+        write_exr_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+        write_exr_attribute(
+            &mut header,
+            "pixelAspectRatio",
+            "float",
+            &1.0f32.to_le_bytes(),
+        );
+        write_exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+        write_exr_attribute(
+            &mut header,
+            "screenWindowWidth",
+            "float",
+            &1.0f32.to_le_bytes(),
+        );
+        header.push(0);
+
+        let width = self.width as usize;
+        let line_data_size = width * CHANNELS.len() * std::mem::size_of::<f32>();
+        let line_block_size = (2 * std::mem::size_of::<i32>() + line_data_size) as u64;
+        let table_size = u64::from(self.height) * std::mem::size_of::<u64>() as u64;
+
+        writer.write_all(&header)?;
+        let first_block = header.len() as u64 + table_size;
+        for y in 0..u64::from(self.height) {
+            writer.write_all(&(first_block + y * line_block_size).to_le_bytes())?;
+        }
+
+        let mut line = Vec::with_capacity(line_data_size);
+        for (y, row) in self.pixels.chunks_exact(width * 4).enumerate() {
+            line.clear();
+            for (_, component) in CHANNELS.iter() {
+                for pixel in row.chunks_exact(4) {
+                    let value = f32::from(pixel[*component]) / 255.0;
+                    line.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            writer.write_all(&(y as i32).to_le_bytes())?;
+            writer.write_all(&(line_data_size as i32).to_le_bytes())?;
+            writer.write_all(&line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Size in bytes of one texel of `format`, for the formats `convert_to_rgba8`
+/// understands.
+pub fn texel_size(format: vk::Format) -> Option<usize> {
+    match format {
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB => Some(4),
+        vk::Format::R8G8B8_UNORM
+        | vk::Format::R8G8B8_SRGB
+        | vk::Format::B8G8R8_UNORM
+        | vk::Format::B8G8R8_SRGB => Some(3),
+        vk::Format::R32G32B32A32_SFLOAT => Some(16),
+        _ => None,
+    }
+}
+
+/// Reorders or expands tightly packed texels of `format` into RGBA8. Float
+/// texels are clamped to 0..1. Three channel formats get an opaque alpha.
+pub fn convert_to_rgba8(format: vk::Format, data: &[u8]) -> Option<Vec<u8>> {
+    let texel_size = texel_size(format)?;
+    let texels = data.chunks_exact(texel_size);
+    let pixels = match format {
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => data.to_vec(),
+        vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => texels
+            .flat_map(|texel| [texel[2], texel[1], texel[0], texel[3]].to_vec())
+            .collect(),
+        vk::Format::R8G8B8_UNORM | vk::Format::R8G8B8_SRGB => texels
+            .flat_map(|texel| [texel[0], texel[1], texel[2], 0xff].to_vec())
+            .collect(),
+        vk::Format::B8G8R8_UNORM | vk::Format::B8G8R8_SRGB => texels
+            .flat_map(|texel| [texel[2], texel[1], texel[0], 0xff].to_vec())
+            .collect(),
+        vk::Format::R32G32B32A32_SFLOAT => texels
+            .flat_map(|texel| {
+                texel.chunks_exact(4).map(|component| {
+                    let value = f32::from_le_bytes([
+                        component[0],
+                        component[1],
+                        component[2],
+                        component[3],
+                    ]);
+                    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+                })
+            })
+            .collect(),
+        _ => return None,
+    };
+    Some(pixels)
+}
+
+fn write_exr_attribute(header: &mut Vec<u8>, name: &str, ty: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(ty.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+fn png_error(err: png::EncodingError) -> io::Error {
+    match err {
+        png::EncodingError::IoError(err) => err,
+        err => io::Error::other(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn image(width: u32, height: u32) -> HostImage {
+        HostImage {
+            width,
+            height,
+            pixels: (0..width * height * 4).map(|value| value as u8).collect(),
+        }
+    }
+
+    fn float_bytes(values: &[f32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn bgra_is_swizzled() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(
+            convert_to_rgba8(vk::Format::B8G8R8A8_UNORM, &data),
+            Some(vec![3, 2, 1, 4, 7, 6, 5, 8])
+        );
+        assert_eq!(
+            convert_to_rgba8(vk::Format::R8G8B8A8_SRGB, &data),
+            Some(data.to_vec())
+        );
+    }
+
+    #[test]
+    fn rgb_gets_opaque_alpha() {
+        let data = [1, 2, 3, 4, 5, 6];
+        assert_eq!(
+            convert_to_rgba8(vk::Format::R8G8B8_UNORM, &data),
+            Some(vec![1, 2, 3, 0xff, 4, 5, 6, 0xff])
+        );
+        assert_eq!(
+            convert_to_rgba8(vk::Format::B8G8R8_SRGB, &data),
+            Some(vec![3, 2, 1, 0xff, 6, 5, 4, 0xff])
+        );
+    }
+
+    #[test]
+    fn floats_are_clamped_and_rounded() {
+        let data = float_bytes(&[-1.0, 0.5, 1.0, 7.5]);
+        assert_eq!(
+            convert_to_rgba8(vk::Format::R32G32B32A32_SFLOAT, &data),
+            Some(vec![0, 128, 255, 255])
+        );
+    }
+
+    #[test]
+    fn short_or_unsupported_data_is_rejected() {
+        assert_eq!(
+            HostImage::from_raw(2, 1, vk::Format::R8G8B8A8_UNORM, &[0; 7]),
+            None
+        );
+        assert_eq!(convert_to_rgba8(vk::Format::D32_SFLOAT, &[0; 4]), None);
+    }
+
+    #[test]
+    fn ppm_drops_alpha() {
+        let mut ppm = Vec::new();
+        image(3, 2).write_ppm(&mut ppm).unwrap();
+        let header = b"P6\n3 2\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 3 * 2 * 3);
+        assert_eq!(&ppm[header.len()..header.len() + 6], &[0, 1, 2, 4, 5, 6]);
+    }
+
+    #[test]
+    fn exr_has_header_offsets_and_scanlines() {
+        let (width, height) = (3, 2);
+        let mut exr = Vec::new();
+        image(width, height).write_exr(&mut exr).unwrap();
+
+        assert_eq!(&exr[..4], &[0x76, 0x2f, 0x31, 0x01]);
+        assert_eq!(&exr[4..8], &2i32.to_le_bytes());
+        // The last attribute is a float, followed by the end of the header
+        let last = b"screenWindowWidth\0float\0";
+        let last_start = exr
+            .windows(last.len())
+            .position(|window| window == last)
+            .unwrap();
+        let header_size = last_start + last.len() + 4 + 4 + 1;
+        assert_eq!(exr[header_size - 1], 0);
+
+        // One offset per scanline, each line holding its y, its size and
+        // four float channels per pixel
+        let table_size = height as usize * 8;
+        let line_size = 4 * width as usize * 4;
+        let first_offset =
+            u64::from_le_bytes(exr[header_size..header_size + 8].try_into().unwrap());
+        assert_eq!(first_offset as usize, header_size + table_size);
+        assert_eq!(
+            exr.len(),
+            header_size + table_size + height as usize * (8 + line_size)
+        );
+
+        // Alpha comes first; pixel 0 has an alpha of 3
+        let first_line = &exr[header_size + table_size..];
+        assert_eq!(&first_line[..4], &0i32.to_le_bytes());
+        assert_eq!(&first_line[4..8], &(line_size as i32).to_le_bytes());
+        assert_eq!(&first_line[8..12], &(3.0f32 / 255.0).to_le_bytes());
+    }
+
+    #[test]
+    fn empty_exr_is_an_error() {
+        let mut exr = Vec::new();
+        let err = image(0, 4).write_exr(&mut exr).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(exr.is_empty());
+    }
+}
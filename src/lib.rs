@@ -0,0 +1,93 @@
+//! NV Vulkan ray tracing with Rust, HLSL, and Ash.
+//!
+//! The crate is split along the steps needed to get rays on screen:
+//!
+//! * [`base`] creates the instance, device and queue, with or without a window.
+//! * [`frame`] keeps several frames in flight, each with its own command
+//!   buffer, fence and semaphores.
+//! * [`allocator`] sub-allocates device memory from large blocks.
+//! * [`resource`] wraps buffers and images with their bound memory.
+//! * [`acceleration_structure`] owns acceleration structures and defines the
+//!   geometry and instance layouts.
+//! * [`camera`] builds the ray generation matrices and flies the camera
+//!   from keyboard and mouse input.
+//! * [`mesh`] loads OBJ meshes and uploads them for BLAS builds.
+//! * [`deform`] runs compute shaders that move the vertices of deforming
+//!   meshes before their BLAS is refitted.
+//! * [`procedural`] uploads AABBs of primitives found by intersection
+//!   shaders.
+//! * [`scene`] groups meshes into models and instances, and imports glTF.
+//! * [`pipeline`] loads shader modules for ray tracing pipelines and creates
+//!   descriptor set layouts for them.
+//! * [`compiler`] compiles HLSL and GLSL sources to SPIR-V at runtime,
+//!   behind the `hlsl-compiler` and `glsl-compiler` features.
+//! * [`path_tracing`] prepares the shading data and parameters of the path
+//!   tracing shaders.
+//! * [`pipeline_cache`] keeps compiled pipelines on disk between runs.
+//! * [`reflect`] reads descriptor bindings back out of SPIR-V.
+//! * [`shader_binding_table`] lays out shader records, with optional inline
+//!   data, for `cmd_trace_rays`.
+//! * [`watch`] polls `shaders/` for edits to hot reload.
+//! * [`image_export`] converts read back frames and writes PNG, PPM or EXR.
+//! * [`error`] defines the `Error` every fallible call returns.
+//! * [`app`] ties these together into the triangle renderer.
+
+#[macro_use]
+extern crate ash;
+#[cfg(target_os = "windows")]
+extern crate winapi;
+extern crate winit;
+
+pub mod acceleration_structure;
+pub mod allocator;
+pub mod app;
+pub mod base;
+pub mod camera;
+pub mod compiler;
+pub mod deform;
+pub mod error;
+pub mod frame;
+pub mod image_export;
+pub mod mesh;
+pub mod path_tracing;
+pub mod pipeline;
+pub mod pipeline_cache;
+pub mod procedural;
+pub mod reflect;
+pub mod resource;
+pub mod scene;
+pub mod shader_binding_table;
+pub mod watch;
+
+pub use crate::acceleration_structure::{
+    Aabb, AccelerationStructure, CompactionStats, GeometryInstance, SceneAccelerationStructures,
+    SceneBuilder, Vertex,
+};
+pub use crate::allocator::{
+    Allocation, AllocationKind, MemoryAllocator, MemoryStats, DEFAULT_BLOCK_SIZE,
+};
+pub use crate::app::{RayTracingApp, ShaderConfig};
+pub use crate::base::{record_submit_commandbuffer, Base, Presentation};
+pub use crate::camera::{Camera, CameraController, CameraUniform, Mat4};
+pub use crate::compiler::{compile_shader, ShaderCompileOptions, ShaderLanguage};
+pub use crate::deform::{DeformParams, VertexDeformer, DEFORM_WORKGROUP_SIZE};
+pub use crate::error::{Error, Result};
+pub use crate::frame::{FrameManager, FrameSlot, FRAMES_IN_FLIGHT};
+pub use crate::image_export::HostImage;
+pub use crate::mesh::{load_obj, Indices, Mesh, MeshBuffers};
+pub use crate::path_tracing::{
+    GeometryRecord, PathTraceParams, ShadingBuffers, ShadingData, DEFAULT_MAX_BOUNCES,
+};
+pub use crate::pipeline::{
+    create_descriptor_set_layouts, load_reflected_shader_module, load_shader_module, read_shader,
+};
+pub use crate::pipeline_cache::PipelineCache;
+pub use crate::procedural::{aabb_geometry_flags, AabbBuffer};
+pub use crate::reflect::{merge_bindings, reflect, DescriptorBinding, ShaderReflection};
+pub use crate::resource::{find_memorytype_index, BufferResource, ImageResource};
+pub use crate::scene::{load_gltf, MeshInstance, Model, Scene};
+pub use crate::shader_binding_table::{
+    ShaderBindingRegion, ShaderBindingTable, ShaderBindingTableBuilder, ShaderBindingTableLayout,
+    ShaderRecord,
+};
+pub use crate::watch::ShaderWatcher;
@@ -0,0 +1,366 @@
+//! Triangle meshes loaded from Wavefront OBJ files, and their device buffers
+//! for bottom-level acceleration structure builds.
+
+use crate::acceleration_structure::Vertex;
+use crate::base::Base;
+use crate::error::Result;
+use crate::resource::BufferResource;
+use ash::vk;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::rc::Rc;
+
+/// Triangle list indices, narrowed to 16 bits when every vertex is
+/// addressable with them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Picks the index width from `vertex_count`.
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Self {
+        if vertex_count <= usize::from(u16::MAX) + 1 {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn index_type(&self) -> vk::IndexType {
+        match self {
+            Indices::U16(_) => vk::IndexType::UINT16,
+            Indices::U32(_) => vk::IndexType::UINT32,
+        }
+    }
+}
+
+/// An indexed triangle list. `normals` and `uvs` always have one entry per
+/// vertex.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh {
+    pub name: String,
+    pub vertices: Vec<Vertex>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Indices,
+}
+
+impl Mesh {
+    /// Builds a mesh from flat arrays. Missing normals are generated by
+    /// averaging face normals, missing UVs are zero.
+    pub fn new(
+        name: String,
+        vertices: Vec<Vertex>,
+        normals: Vec<[f32; 3]>,
+        uvs: Vec<[f32; 2]>,
+        indices: Vec<u32>,
+    ) -> Self {
+        let normals = if normals.len() == vertices.len() {
+            normals
+        } else {
+            smooth_normals(&vertices, &indices)
+        };
+        let uvs = if uvs.len() == vertices.len() {
+            uvs
+        } else {
+            vec![[0.0, 0.0]; vertices.len()]
+        };
+        let indices = Indices::new(indices, vertices.len());
+        Mesh {
+            name,
+            vertices,
+            normals,
+            uvs,
+            indices,
+        }
+    }
+
+    /// The single triangle rendered when no scene is loaded.
+    pub fn triangle() -> Self {
+        let vertices = vec![
+            Vertex {
+                pos: [-0.5, -0.5, 0.0],
+            },
+            Vertex {
+                pos: [0.0, 0.5, 0.0],
+            },
+            Vertex {
+                pos: [0.5, -0.5, 0.0],
+            },
+        ];
+        Mesh::new(
+            "triangle".to_owned(),
+            vertices,
+            vec![[0.0, 0.0, 1.0]; 3],
+            vec![[0.0, 0.0], [0.5, 1.0], [1.0, 0.0]],
+            vec![0, 1, 2],
+        )
+    }
+
+    fn from_model(model: tobj::Model) -> Self {
+        let mesh = model.mesh;
+        let vertices = mesh
+            .positions
+            .chunks_exact(3)
+            .map(|pos| Vertex {
+                pos: [pos[0], pos[1], pos[2]],
+            })
+            .collect();
+        let normals = mesh
+            .normals
+            .chunks_exact(3)
+            .map(|normal| [normal[0], normal[1], normal[2]])
+            .collect();
+        let uvs = mesh
+            .texcoords
+            .chunks_exact(2)
+            .map(|uv| [uv[0], uv[1]])
+            .collect();
+        Mesh::new(model.name, vertices, normals, uvs, mesh.indices)
+    }
+}
+
+/// Loads every object and group in an OBJ file as a separate mesh.
+/// Polygons are triangulated; points, lines and materials are ignored.
+pub fn load_obj(path: &Path) -> Result<Vec<Mesh>> {
+    parse_obj(&mut BufReader::new(File::open(path)?))
+}
+
+/// Parses OBJ source, see `load_obj`.
+pub fn parse_obj<R: BufRead>(reader: &mut R) -> Result<Vec<Mesh>> {
+    let (models, _) = tobj::load_obj_buf(reader, &tobj::GPU_LOAD_OPTIONS, |_| {
+        Ok((Vec::new(), HashMap::new()))
+    })?;
+    Ok(models
+        .into_iter()
+        .filter(|model| !model.mesh.indices.is_empty())
+        .map(Mesh::from_model)
+        .collect())
+}
+
+fn smooth_normals(vertices: &[Vertex], indices: &[u32]) -> Vec<[f32; 3]> {
+    let mut normals = vec![[0.0f32; 3]; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        // Loaders reject out of range indices, skip them rather than panic
+        let [a, b, c] = match [
+            vertices.get(triangle[0] as usize),
+            vertices.get(triangle[1] as usize),
+            vertices.get(triangle[2] as usize),
+        ] {
+            [Some(a), Some(b), Some(c)] => [a.pos, b.pos, c.pos],
+            _ => continue,
+        };
+        let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        // Area weighted: the cross product is left unnormalized.
+        let face_normal = [
+            ab[1] * ac[2] - ab[2] * ac[1],
+            ab[2] * ac[0] - ab[0] * ac[2],
+            ab[0] * ac[1] - ab[1] * ac[0],
+        ];
+        for &index in triangle {
+            let normal = &mut normals[index as usize];
+            for axis in 0..3 {
+                normal[axis] += face_normal[axis];
+            }
+        }
+    }
+    for normal in &mut normals {
+        let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+        if length > 0.0 {
+            for component in normal.iter_mut() {
+                *component /= length;
+            }
+        }
+    }
+    normals
+}
+
+/// Vertex and index buffers of a mesh, uploaded for an acceleration
+/// structure build.
+pub struct MeshBuffers {
+    pub vertex_buffer: BufferResource,
+    pub index_buffer: BufferResource,
+    /// Undeformed positions read by the deformation pass, only set for
+    /// meshes created with `new_deformable`.
+    pub rest_vertex_buffer: Option<BufferResource>,
+    pub vertex_count: u32,
+    pub index_count: u32,
+    pub index_type: vk::IndexType,
+}
+
+impl MeshBuffers {
+    pub fn new(mesh: &Mesh, base: Rc<Base>) -> Result<Self> {
+        let mut vertex_buffer = BufferResource::new(
+            vertex_buffer_size(mesh),
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            base.clone(),
+        )?;
+        vertex_buffer.store(&mesh.vertices)?;
+
+        MeshBuffers::with_vertex_buffers(mesh, vertex_buffer, None, base)
+    }
+
+    /// Uploads the vertices as rest pose for a `VertexDeformer`, which
+    /// writes the deformed positions to a device local `vertex_buffer`.
+    /// That buffer is undefined until the deformer has run once.
+    pub fn new_deformable(mesh: &Mesh, base: Rc<Base>) -> Result<Self> {
+        let mut rest_vertex_buffer = BufferResource::new(
+            vertex_buffer_size(mesh),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            base.clone(),
+        )?;
+        rest_vertex_buffer.store(&mesh.vertices)?;
+
+        let vertex_buffer = BufferResource::new(
+            vertex_buffer_size(mesh),
+            vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            base.clone(),
+        )?;
+
+        MeshBuffers::with_vertex_buffers(mesh, vertex_buffer, Some(rest_vertex_buffer), base)
+    }
+
+    fn with_vertex_buffers(
+        mesh: &Mesh,
+        vertex_buffer: BufferResource,
+        rest_vertex_buffer: Option<BufferResource>,
+        base: Rc<Base>,
+    ) -> Result<Self> {
+        let index_buffer_size = match &mesh.indices {
+            Indices::U16(indices) => std::mem::size_of_val(indices.as_slice()),
+            Indices::U32(indices) => std::mem::size_of_val(indices.as_slice()),
+        };
+        let mut index_buffer = BufferResource::new(
+            index_buffer_size as u64,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            base,
+        )?;
+        match &mesh.indices {
+            Indices::U16(indices) => index_buffer.store(indices)?,
+            Indices::U32(indices) => index_buffer.store(indices)?,
+        }
+
+        Ok(MeshBuffers {
+            vertex_buffer,
+            index_buffer,
+            rest_vertex_buffer,
+            vertex_count: mesh.vertices.len() as u32,
+            index_count: mesh.indices.len() as u32,
+            index_type: mesh.indices.index_type(),
+        })
+    }
+
+    pub fn is_deformable(&self) -> bool {
+        self.rest_vertex_buffer.is_some()
+    }
+
+    /// Describes the buffers as one opaque triangle geometry of a
+    /// bottom-level acceleration structure.
+    pub fn geometry(&self) -> vk::GeometryNV {
+        vk::GeometryNV::builder()
+            .geometry_type(vk::GeometryTypeNV::TRIANGLES)
+            .geometry(
+                vk::GeometryDataNV::builder()
+                    .triangles(
+                        vk::GeometryTrianglesNV::builder()
+                            .vertex_data(self.vertex_buffer.buffer)
+                            .vertex_offset(0)
+                            .vertex_count(self.vertex_count)
+                            .vertex_stride(std::mem::size_of::<Vertex>() as u64)
+                            .vertex_format(vk::Format::R32G32B32_SFLOAT)
+                            .index_data(self.index_buffer.buffer)
+                            .index_offset(0)
+                            .index_count(self.index_count)
+                            .index_type(self.index_type)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .flags(vk::GeometryFlagsNV::OPAQUE)
+            .build()
+    }
+}
+
+fn vertex_buffer_size(mesh: &Mesh) -> vk::DeviceSize {
+    std::mem::size_of_val(mesh.vertices.as_slice()) as vk::DeviceSize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD_AND_TRIANGLE: &str = "
+o quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g triangle
+v 0 0 1
+v 1 0 1
+v 0 1 1
+f 5 6 7
+";
+
+    #[test]
+    fn index_width_follows_vertex_count() {
+        assert_eq!(Indices::new(vec![0, 1, 2], 3), Indices::U16(vec![0, 1, 2]));
+        assert_eq!(
+            Indices::new(vec![0, 1, 65535], 65536).index_type(),
+            vk::IndexType::UINT16
+        );
+        assert_eq!(
+            Indices::new(vec![0, 1, 65536], 65537),
+            Indices::U32(vec![0, 1, 65536])
+        );
+    }
+
+    #[test]
+    fn objects_and_groups_become_meshes() {
+        let meshes = parse_obj(&mut QUAD_AND_TRIANGLE.as_bytes()).unwrap();
+        let names: Vec<_> = meshes.iter().map(|mesh| mesh.name.as_str()).collect();
+        assert_eq!(names, ["quad", "triangle"]);
+
+        let quad = &meshes[0];
+        assert_eq!(quad.vertices.len(), 4);
+        assert_eq!(quad.indices.len(), 6);
+        assert_eq!(quad.indices.index_type(), vk::IndexType::UINT16);
+        assert_eq!(quad.uvs[2], [1.0, 1.0]);
+        assert_eq!(quad.normals, vec![[0.0, 0.0, 1.0]; 4]);
+    }
+
+    #[test]
+    fn missing_attributes_are_filled_in() {
+        let meshes = parse_obj(&mut QUAD_AND_TRIANGLE.as_bytes()).unwrap();
+        let triangle = &meshes[1];
+        assert_eq!(triangle.vertices.len(), 3);
+        assert_eq!(triangle.uvs, vec![[0.0, 0.0]; 3]);
+        assert_eq!(triangle.normals, vec![[0.0, 0.0, 1.0]; 3]);
+    }
+}
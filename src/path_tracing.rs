@@ -0,0 +1,179 @@
+//! Parameters and shading data for the progressive path tracing shaders,
+//! `shaders/pathtrace.*.hlsl`.
+
+use crate::base::Base;
+use crate::error::Result;
+use crate::mesh::Indices;
+use crate::resource::BufferResource;
+use crate::scene::Scene;
+use ash::vk;
+use std::rc::Rc;
+
+/// Bounces traced per sample unless `RayTracingApp::set_max_bounces` says
+/// otherwise.
+pub const DEFAULT_MAX_BOUNCES: u32 = 4;
+
+/// Push constants passed to the path tracing ray generation shader.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PathTraceParams {
+    /// Frames already averaged into the accumulation image; zero restarts
+    /// the average.
+    pub frame_index: u32,
+    pub max_bounces: u32,
+}
+
+/// Inline data of a triangle hit record: where the geometry's normals and
+/// indices start in the shading buffers.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GeometryRecord {
+    pub first_vertex: u32,
+    pub first_index: u32,
+}
+
+/// The per-vertex normals and indices of every mesh in a scene,
+/// concatenated for the closest hit shader, and the hit records locating
+/// each mesh in them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShadingData {
+    /// Object space normals, padded to `float4` for `StructuredBuffer`.
+    pub normals: Vec<[f32; 4]>,
+    /// Triangle list indices, relative to the mesh's first vertex.
+    pub indices: Vec<u32>,
+    /// One record per geometry of each model, in hit record order.
+    /// Procedural models get a single record that is never read.
+    pub geometry_records: Vec<Vec<GeometryRecord>>,
+}
+
+impl ShadingData {
+    pub fn new(scene: &Scene) -> Self {
+        let mut data = ShadingData::default();
+        for model in &scene.models {
+            if model.is_procedural() {
+                data.geometry_records.push(vec![GeometryRecord::default()]);
+                continue;
+            }
+            let mut records = Vec::new();
+            for mesh in &model.meshes {
+                records.push(GeometryRecord {
+                    first_vertex: data.normals.len() as u32,
+                    first_index: data.indices.len() as u32,
+                });
+                data.normals.extend(
+                    mesh.normals
+                        .iter()
+                        .map(|normal| [normal[0], normal[1], normal[2], 0.0]),
+                );
+                match &mesh.indices {
+                    Indices::U16(indices) => data
+                        .indices
+                        .extend(indices.iter().map(|&index| u32::from(index))),
+                    Indices::U32(indices) => data.indices.extend(indices),
+                }
+            }
+            data.geometry_records.push(records);
+        }
+        data
+    }
+
+    /// Index of each model's first hit record, the hit group offset of its
+    /// instances.
+    pub fn first_hit_records(&self) -> Vec<u32> {
+        self.geometry_records
+            .iter()
+            .scan(0, |first, records| {
+                let model_first = *first;
+                *first += records.len() as u32;
+                Some(model_first)
+            })
+            .collect()
+    }
+}
+
+/// `ShadingData` uploaded as the storage buffers at bindings 5 (normals)
+/// and 6 (indices).
+pub struct ShadingBuffers {
+    pub normal_buffer: BufferResource,
+    pub index_buffer: BufferResource,
+}
+
+impl ShadingBuffers {
+    pub fn new(data: &ShadingData, base: Rc<Base>) -> Result<Self> {
+        Ok(ShadingBuffers {
+            normal_buffer: storage_buffer(&data.normals, base.clone())?,
+            index_buffer: storage_buffer(&data.indices, base)?,
+        })
+    }
+}
+
+/// A host visible storage buffer holding `data`. Buffers can't be empty,
+/// so scenes without triangles get a small unused one.
+fn storage_buffer<T: Copy>(data: &[T], base: Rc<Base>) -> Result<BufferResource> {
+    let size = std::mem::size_of_val(data).max(16) as vk::DeviceSize;
+    let mut buffer = BufferResource::new(
+        size,
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        base,
+    )?;
+    if !data.is_empty() {
+        buffer.store(data)?;
+    }
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acceleration_structure::Aabb;
+    use crate::mesh::Mesh;
+    use crate::scene::Model;
+
+    #[test]
+    fn meshes_are_concatenated_with_one_record_each() {
+        let mut scene = Scene::triangles();
+        scene.models[0].meshes.push(Mesh::triangle());
+        scene.models.push(Model {
+            name: "sphere".to_owned(),
+            meshes: Vec::new(),
+            aabbs: vec![Aabb {
+                min: [-1.0; 3],
+                max: [1.0; 3],
+            }],
+            deformable: false,
+        });
+        scene.models.push(Model {
+            name: "triangle".to_owned(),
+            meshes: vec![Mesh::triangle()],
+            aabbs: Vec::new(),
+            deformable: false,
+        });
+
+        let data = ShadingData::new(&scene);
+        assert_eq!(data.normals.len(), 9);
+        assert_eq!(data.indices.len(), 9);
+        assert_eq!(&data.indices[3..6], &data.indices[..3]);
+        assert_eq!(
+            data.geometry_records,
+            vec![
+                vec![
+                    GeometryRecord {
+                        first_vertex: 0,
+                        first_index: 0,
+                    },
+                    GeometryRecord {
+                        first_vertex: 3,
+                        first_index: 3,
+                    },
+                ],
+                vec![GeometryRecord::default()],
+                vec![GeometryRecord {
+                    first_vertex: 6,
+                    first_index: 6,
+                }],
+            ]
+        );
+        assert_eq!(data.first_hit_records(), vec![0, 2, 3]);
+    }
+}
@@ -0,0 +1,117 @@
+//! Shader module loading and descriptor set layouts for ray tracing
+//! pipelines.
+
+use crate::compiler::{compile_shader, ShaderCompileOptions, ShaderLanguage};
+use crate::error::{Error, Result};
+use crate::reflect::{reflect, DescriptorBinding, ShaderReflection};
+use ash::util::read_spv;
+use ash::version::DeviceV1_0;
+use ash::{vk, Device};
+use std::fs::File;
+use std::path::Path;
+
+/// Reads a SPIR-V binary from disk. `.hlsl` and `.glsl` sources are
+/// compiled with the default `ShaderCompileOptions` instead.
+pub fn read_shader(path: &Path) -> Result<Vec<u32>> {
+    if ShaderLanguage::from_path(path).is_some() {
+        return compile_shader(path, &ShaderCompileOptions::default());
+    }
+    File::open(path)
+        .and_then(|mut file| read_spv(&mut file))
+        .map_err(|source| Error::ShaderLoad {
+            path: path.to_owned(),
+            source,
+        })
+}
+
+/// Reads a SPIR-V binary from disk and creates a shader module from it.
+pub fn load_shader_module(device: &Device, path: &Path) -> Result<vk::ShaderModule> {
+    let code = read_shader(path)?;
+    create_shader_module(device, &code)
+}
+
+/// Like `load_shader_module`, also returning the descriptor bindings the
+/// module declares.
+pub fn load_reflected_shader_module(
+    device: &Device,
+    path: &Path,
+) -> Result<(vk::ShaderModule, ShaderReflection)> {
+    let code = read_shader(path)?;
+    let reflection = reflect(&code)?;
+    Ok((create_shader_module(device, &code)?, reflection))
+}
+
+fn create_shader_module(device: &Device, code: &[u32]) -> Result<vk::ShaderModule> {
+    let shader_info = vk::ShaderModuleCreateInfo::builder().code(code);
+    unsafe { Ok(device.create_shader_module(&shader_info, None)?) }
+}
+
+/// Creates one layout per set from `0` to the highest set in `bindings`,
+/// as merged by `merge_bindings`. Runtime sized arrays get
+/// `runtime_array_count` descriptors and `VARIABLE_DESCRIPTOR_COUNT`.
+pub fn create_descriptor_set_layouts(
+    device: &Device,
+    bindings: &[DescriptorBinding],
+    runtime_array_count: u32,
+) -> Result<Vec<vk::DescriptorSetLayout>> {
+    let set_count = bindings
+        .iter()
+        .map(|binding| binding.set + 1)
+        .max()
+        .unwrap_or(0);
+    let mut layouts = Vec::new();
+    for set in 0..set_count {
+        let set_bindings: Vec<&DescriptorBinding> = bindings
+            .iter()
+            .filter(|binding| binding.set == set)
+            .collect();
+        let layout_bindings: Vec<vk::DescriptorSetLayoutBinding> = set_bindings
+            .iter()
+            .map(|binding| {
+                let count = if binding.is_runtime_array() {
+                    runtime_array_count
+                } else {
+                    binding.count
+                };
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(binding.binding)
+                    .descriptor_type(binding.descriptor_type)
+                    .descriptor_count(count)
+                    .stage_flags(binding.stages)
+                    .build()
+            })
+            .collect();
+        let flags: Vec<vk::DescriptorBindingFlagsEXT> = set_bindings
+            .iter()
+            .map(|binding| {
+                if binding.is_runtime_array() {
+                    vk::DescriptorBindingFlagsEXT::VARIABLE_DESCRIPTOR_COUNT
+                } else {
+                    vk::DescriptorBindingFlagsEXT::empty()
+                }
+            })
+            .collect();
+        let mut binding_flags = vk::DescriptorSetLayoutBindingFlagsCreateInfoEXT::builder()
+            .binding_flags(&flags)
+            .build();
+
+        let layout = unsafe {
+            device.create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::builder()
+                    .bindings(&layout_bindings)
+                    .push_next(&mut binding_flags),
+                None,
+            )
+        };
+        match layout {
+            Ok(layout) => layouts.push(layout),
+            Err(err) => {
+                for layout in layouts {
+                    unsafe { device.destroy_descriptor_set_layout(layout, None) };
+                }
+                return Err(err.into());
+            }
+        }
+    }
+    Ok(layouts)
+}
@@ -0,0 +1,143 @@
+//! A `vk::PipelineCache` persisted to disk between runs.
+
+use crate::base::Base;
+use crate::error::Result;
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Size of `VkPipelineCacheHeaderVersionOne`: header size, header version,
+/// vendor ID and device ID as `u32`s, then the pipeline cache UUID.
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+/// Pipeline cache seeded from, and saved back to, a file.
+///
+/// Data written by another driver or device is ignored on load, so a stale
+/// file only costs the time to rebuild it.
+pub struct PipelineCache {
+    pub cache: vk::PipelineCache,
+    path: Option<PathBuf>,
+    base: Rc<Base>,
+}
+
+impl PipelineCache {
+    /// Creates a cache, seeded from `path` if it exists and its header
+    /// matches this device. With no path the cache lives in memory only.
+    pub fn new(path: Option<&Path>, base: Rc<Base>) -> Result<Self> {
+        let mut initial_data = match path {
+            Some(path) => match fs::read(path) {
+                Ok(data) => data,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(err) => return Err(err.into()),
+            },
+            None => Vec::new(),
+        };
+        let properties = unsafe { base.instance.get_physical_device_properties(base.pdevice) };
+        if !header_matches(&initial_data, &properties) {
+            initial_data.clear();
+        }
+
+        let cache = unsafe {
+            base.device.create_pipeline_cache(
+                &vk::PipelineCacheCreateInfo::builder().initial_data(&initial_data),
+                None,
+            )?
+        };
+        Ok(PipelineCache {
+            cache,
+            path: path.map(Path::to_owned),
+            base,
+        })
+    }
+
+    /// Writes the cache contents to its file, if it has one. The file is
+    /// replaced in one rename so an interrupted save leaves the old data.
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let data = unsafe { self.base.device.get_pipeline_cache_data(self.cache)? };
+            let temporary_path = path.with_extension("tmp");
+            fs::write(&temporary_path, data)?;
+            fs::rename(&temporary_path, path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PipelineCache {
+    fn drop(&mut self) {
+        unsafe {
+            self.base.device.destroy_pipeline_cache(self.cache, None);
+        }
+    }
+}
+
+/// Whether `data` starts with a version one header written by the device
+/// described by `properties`.
+fn header_matches(data: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+    let word = |index: usize| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&data[4 * index..4 * index + 4]);
+        u32::from_ne_bytes(bytes)
+    };
+    word(0) as usize >= HEADER_SIZE
+        && word(1) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && word(2) == properties.vendor_id
+        && word(3) == properties.device_id
+        && data[16..HEADER_SIZE] == properties.pipeline_cache_uuid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties() -> vk::PhysicalDeviceProperties {
+        vk::PhysicalDeviceProperties {
+            vendor_id: 0x10de,
+            device_id: 0x1e07,
+            pipeline_cache_uuid: [7; vk::UUID_SIZE],
+            ..Default::default()
+        }
+    }
+
+    fn header(properties: &vk::PhysicalDeviceProperties) -> Vec<u8> {
+        let mut data = Vec::new();
+        for word in &[
+            HEADER_SIZE as u32,
+            1,
+            properties.vendor_id,
+            properties.device_id,
+        ] {
+            data.extend_from_slice(&word.to_ne_bytes());
+        }
+        data.extend_from_slice(&properties.pipeline_cache_uuid);
+        data.extend_from_slice(&[0xab; 64]);
+        data
+    }
+
+    #[test]
+    fn accepts_data_from_the_same_device() {
+        let properties = properties();
+        assert!(header_matches(&header(&properties), &properties));
+    }
+
+    #[test]
+    fn rejects_other_devices_and_drivers() {
+        let properties = properties();
+        let mut other_device = properties;
+        other_device.device_id += 1;
+        assert!(!header_matches(&header(&other_device), &properties));
+
+        let mut other_driver = properties;
+        other_driver.pipeline_cache_uuid[0] = 8;
+        assert!(!header_matches(&header(&other_driver), &properties));
+
+        assert!(!header_matches(&header(&properties)[..20], &properties));
+        assert!(!header_matches(&[], &properties));
+    }
+}
@@ -0,0 +1,113 @@
+//! Device buffers of axis-aligned boxes bounding procedural primitives, for
+//! AABB geometry in bottom-level acceleration structures.
+
+use crate::acceleration_structure::Aabb;
+use crate::base::Base;
+use crate::error::Result;
+use crate::resource::BufferResource;
+use ash::vk;
+use std::rc::Rc;
+
+/// AABBs uploaded for an acceleration structure build. Rays entering a box
+/// run the intersection shader of the instance's procedural hit group.
+pub struct AabbBuffer {
+    pub buffer: BufferResource,
+    pub aabb_count: u32,
+}
+
+impl AabbBuffer {
+    pub fn new(aabbs: &[Aabb], base: Rc<Base>) -> Result<Self> {
+        let mut buffer = BufferResource::new(
+            std::mem::size_of_val(aabbs) as vk::DeviceSize,
+            vk::BufferUsageFlags::RAY_TRACING_NV,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            base,
+        )?;
+        buffer.store(aabbs)?;
+        Ok(AabbBuffer {
+            buffer,
+            aabb_count: aabbs.len() as u32,
+        })
+    }
+
+    /// Describes the boxes as one AABB geometry of a bottom-level
+    /// acceleration structure. Any-hit shaders only run without `OPAQUE`.
+    pub fn geometry(&self, flags: vk::GeometryFlagsNV) -> vk::GeometryNV {
+        aabb_geometry(self.buffer.buffer, self.aabb_count, flags)
+    }
+}
+
+/// Flags for AABB geometry whose procedural hit group does or does not have
+/// an any-hit shader, which would never run on `OPAQUE` geometry.
+pub fn aabb_geometry_flags(has_any_hit_shader: bool) -> vk::GeometryFlagsNV {
+    if has_any_hit_shader {
+        vk::GeometryFlagsNV::empty()
+    } else {
+        vk::GeometryFlagsNV::OPAQUE
+    }
+}
+
+fn aabb_geometry(
+    buffer: vk::Buffer,
+    aabb_count: u32,
+    flags: vk::GeometryFlagsNV,
+) -> vk::GeometryNV {
+    vk::GeometryNV::builder()
+        .geometry_type(vk::GeometryTypeNV::AABBS)
+        .geometry(
+            vk::GeometryDataNV::builder()
+                .aabbs(
+                    vk::GeometryAABBNV::builder()
+                        .aabb_data(buffer)
+                        .num_aab_bs(aabb_count)
+                        .stride(std::mem::size_of::<Aabb>() as u32)
+                        .offset(0)
+                        .build(),
+                )
+                .build(),
+        )
+        .flags(flags)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    fn aabbs_are_packed_as_six_floats() {
+        // VkAabbPositionsKHR: min x, y, z then max x, y, z
+        assert_eq!(std::mem::size_of::<Aabb>(), 24);
+        let aabbs = [
+            Aabb {
+                min: [-1.0; 3],
+                max: [1.0; 3],
+            },
+            Aabb {
+                min: [2.0, 0.0, 0.0],
+                max: [3.0, 1.0, 1.0],
+            },
+        ];
+        assert_eq!(std::mem::size_of_val(&aabbs), 48);
+    }
+
+    #[test]
+    fn geometry_points_at_every_box() {
+        let buffer = vk::Buffer::from_raw(42);
+        let geometry = aabb_geometry(buffer, 3, aabb_geometry_flags(false));
+        assert_eq!(geometry.geometry_type, vk::GeometryTypeNV::AABBS);
+        assert_eq!(geometry.flags, vk::GeometryFlagsNV::OPAQUE);
+        let aabbs = geometry.geometry.aabbs;
+        assert_eq!(aabbs.aabb_data, buffer);
+        assert_eq!(aabbs.num_aab_bs, 3);
+        assert_eq!(aabbs.stride, 24);
+        assert_eq!(aabbs.offset, 0);
+    }
+
+    #[test]
+    fn any_hit_shaders_need_non_opaque_geometry() {
+        assert_eq!(aabb_geometry_flags(true), vk::GeometryFlagsNV::empty());
+        assert_eq!(aabb_geometry_flags(false), vk::GeometryFlagsNV::OPAQUE);
+    }
+}
@@ -0,0 +1,446 @@
+//! Descriptor bindings reflected from SPIR-V, so descriptor set layouts can
+//! follow the shaders instead of being written out by hand.
+//!
+//! Only the handful of instructions that describe resources are decoded:
+//! entry points, `DescriptorSet`/`Binding` decorations, resource types and
+//! the variables declared with them.
+
+use crate::error::{Error, Result};
+use ash::vk;
+use std::collections::HashMap;
+
+const MAGIC: u32 = 0x0723_0203;
+const HEADER_WORDS: usize = 5;
+
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_TYPE_ACCELERATION_STRUCTURE_NV: u32 = 5341;
+
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+const DIM_BUFFER: u32 = 5;
+
+/// One descriptor binding used by a shader.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    /// Array length, or 0 for a runtime sized (bindless) array.
+    pub count: u32,
+    pub stages: vk::ShaderStageFlags,
+}
+
+impl DescriptorBinding {
+    pub fn is_runtime_array(&self) -> bool {
+        self.count == 0
+    }
+}
+
+/// What a SPIR-V module declares.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShaderReflection {
+    /// Stages of all entry points; library modules have several.
+    pub stages: vk::ShaderStageFlags,
+    /// Sorted by set, then binding.
+    pub bindings: Vec<DescriptorBinding>,
+}
+
+#[derive(Clone, Copy)]
+enum Type {
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct,
+    Pointer { storage_class: u32, pointee: u32 },
+    AccelerationStructure,
+}
+
+/// Reflects the descriptor bindings of every resource variable in `code`.
+/// Bindings are attributed to all stages of the module.
+pub fn reflect(code: &[u32]) -> Result<ShaderReflection> {
+    if code.len() < HEADER_WORDS || code[0] != MAGIC {
+        return Err(Error::Reflection("Not a SPIR-V module".to_owned()));
+    }
+
+    let mut stages = vk::ShaderStageFlags::empty();
+    let mut types = HashMap::new();
+    let mut constants = HashMap::new();
+    let mut sets = HashMap::new();
+    let mut bindings = HashMap::new();
+    let mut buffer_blocks = Vec::new();
+    // (variable, pointer type)
+    let mut variables = Vec::new();
+
+    let mut words = &code[HEADER_WORDS..];
+    while !words.is_empty() {
+        let word_count = (words[0] >> 16) as usize;
+        let opcode = words[0] & 0xffff;
+        if word_count == 0 || word_count > words.len() {
+            return Err(Error::Reflection("Truncated instruction".to_owned()));
+        }
+        let operands = &words[1..word_count];
+        let operand = |index: usize| {
+            operands
+                .get(index)
+                .copied()
+                .ok_or_else(|| Error::Reflection(format!("Missing operand of opcode {}", opcode)))
+        };
+
+        match opcode {
+            OP_ENTRY_POINT => stages |= execution_model_stage(operand(0)?)?,
+            OP_DECORATE => match operand(1)? {
+                DECORATION_DESCRIPTOR_SET => {
+                    sets.insert(operand(0)?, operand(2)?);
+                }
+                DECORATION_BINDING => {
+                    bindings.insert(operand(0)?, operand(2)?);
+                }
+                DECORATION_BUFFER_BLOCK => buffer_blocks.push(operand(0)?),
+                _ => {}
+            },
+            OP_TYPE_IMAGE => {
+                let ty = Type::Image {
+                    dim: operand(2)?,
+                    sampled: operand(6)?,
+                };
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_SAMPLER => {
+                types.insert(operand(0)?, Type::Sampler);
+            }
+            OP_TYPE_SAMPLED_IMAGE => {
+                types.insert(operand(0)?, Type::SampledImage);
+            }
+            OP_TYPE_ARRAY => {
+                let ty = Type::Array {
+                    element: operand(1)?,
+                    length: operand(2)?,
+                };
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_RUNTIME_ARRAY => {
+                let ty = Type::RuntimeArray {
+                    element: operand(1)?,
+                };
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_STRUCT => {
+                types.insert(operand(0)?, Type::Struct);
+            }
+            OP_TYPE_POINTER => {
+                let ty = Type::Pointer {
+                    storage_class: operand(1)?,
+                    pointee: operand(2)?,
+                };
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_ACCELERATION_STRUCTURE_NV => {
+                types.insert(operand(0)?, Type::AccelerationStructure);
+            }
+            OP_CONSTANT => {
+                // 32-bit lengths are all array sizes need
+                constants.insert(operand(1)?, operand(2)?);
+            }
+            OP_VARIABLE => variables.push((operand(1)?, operand(0)?)),
+            _ => {}
+        }
+
+        words = &words[word_count..];
+    }
+
+    let mut reflected = Vec::new();
+    for (variable, pointer_type) in variables {
+        let (set, binding) = match (sets.get(&variable), bindings.get(&variable)) {
+            (Some(&set), Some(&binding)) => (set, binding),
+            _ => continue,
+        };
+        let (storage_class, mut ty) = match types.get(&pointer_type) {
+            Some(&Type::Pointer {
+                storage_class,
+                pointee,
+            }) => (storage_class, pointee),
+            _ => {
+                return Err(Error::Reflection(format!(
+                    "Variable %{} is not a pointer",
+                    variable
+                )))
+            }
+        };
+
+        let mut count = 1;
+        loop {
+            match types.get(&ty) {
+                Some(&Type::Array { element, length }) => {
+                    count *= constants.get(&length).copied().ok_or_else(|| {
+                        Error::Reflection(format!("Array length %{} is not a constant", length))
+                    })?;
+                    ty = element;
+                }
+                Some(&Type::RuntimeArray { element }) => {
+                    count = 0;
+                    ty = element;
+                }
+                _ => break,
+            }
+        }
+
+        let descriptor_type = match types.get(&ty) {
+            Some(Type::AccelerationStructure) => vk::DescriptorType::ACCELERATION_STRUCTURE_NV,
+            Some(Type::Sampler) => vk::DescriptorType::SAMPLER,
+            Some(Type::SampledImage) => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            Some(&Type::Image { dim, sampled }) => match (dim == DIM_BUFFER, sampled == 2) {
+                (false, false) => vk::DescriptorType::SAMPLED_IMAGE,
+                (false, true) => vk::DescriptorType::STORAGE_IMAGE,
+                (true, false) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                (true, true) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+            },
+            Some(Type::Struct) => match storage_class {
+                STORAGE_CLASS_STORAGE_BUFFER => vk::DescriptorType::STORAGE_BUFFER,
+                STORAGE_CLASS_UNIFORM if buffer_blocks.contains(&ty) => {
+                    vk::DescriptorType::STORAGE_BUFFER
+                }
+                _ => vk::DescriptorType::UNIFORM_BUFFER,
+            },
+            _ => {
+                return Err(Error::Reflection(format!(
+                    "Unsupported resource type of variable %{}",
+                    variable
+                )))
+            }
+        };
+
+        reflected.push(DescriptorBinding {
+            set,
+            binding,
+            descriptor_type,
+            count,
+            stages,
+        });
+    }
+    reflected.sort_by_key(|binding| (binding.set, binding.binding));
+
+    Ok(ShaderReflection {
+        stages,
+        bindings: reflected,
+    })
+}
+
+/// Unions the bindings of several modules, e.g. all stages of a pipeline,
+/// combining their stage flags. Fails when two modules declare the same
+/// binding with different types or counts.
+pub fn merge_bindings(reflections: &[ShaderReflection]) -> Result<Vec<DescriptorBinding>> {
+    let mut merged: Vec<DescriptorBinding> = Vec::new();
+    for binding in reflections
+        .iter()
+        .flat_map(|reflection| &reflection.bindings)
+    {
+        match merged
+            .iter_mut()
+            .find(|other| (other.set, other.binding) == (binding.set, binding.binding))
+        {
+            Some(other) => {
+                if (other.descriptor_type, other.count) != (binding.descriptor_type, binding.count)
+                {
+                    return Err(Error::Reflection(format!(
+                        "Set {} binding {} is declared as {} {:?} and {} {:?}",
+                        binding.set,
+                        binding.binding,
+                        other.count,
+                        other.descriptor_type,
+                        binding.count,
+                        binding.descriptor_type
+                    )));
+                }
+                other.stages |= binding.stages;
+            }
+            None => merged.push(*binding),
+        }
+    }
+    merged.sort_by_key(|binding| (binding.set, binding.binding));
+    Ok(merged)
+}
+
+fn execution_model_stage(execution_model: u32) -> Result<vk::ShaderStageFlags> {
+    Ok(match execution_model {
+        0 => vk::ShaderStageFlags::VERTEX,
+        1 => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+        2 => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+        3 => vk::ShaderStageFlags::GEOMETRY,
+        4 => vk::ShaderStageFlags::FRAGMENT,
+        5 => vk::ShaderStageFlags::COMPUTE,
+        5313 => vk::ShaderStageFlags::RAYGEN_NV,
+        5314 => vk::ShaderStageFlags::INTERSECTION_NV,
+        5315 => vk::ShaderStageFlags::ANY_HIT_NV,
+        5316 => vk::ShaderStageFlags::CLOSEST_HIT_NV,
+        5317 => vk::ShaderStageFlags::MISS_NV,
+        5318 => vk::ShaderStageFlags::CALLABLE_NV,
+        _ => {
+            return Err(Error::Reflection(format!(
+                "Unknown execution model {}",
+                execution_model
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::util::read_spv;
+    use std::fs::File;
+    use std::path::Path;
+
+    fn reflect_compiled(name: &str) -> ShaderReflection {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("shaders/compiled")
+            .join(name);
+        let code = read_spv(&mut File::open(path).unwrap()).unwrap();
+        reflect(&code).unwrap()
+    }
+
+    fn binding(
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        count: u32,
+        stages: vk::ShaderStageFlags,
+    ) -> DescriptorBinding {
+        DescriptorBinding {
+            set: 0,
+            binding,
+            descriptor_type,
+            count,
+            stages,
+        }
+    }
+
+    #[test]
+    fn raygen_declares_top_level_output_image_and_camera() {
+        let reflection = reflect_compiled("triangle.glsl_rgen.spv");
+        assert_eq!(reflection.stages, vk::ShaderStageFlags::RAYGEN_NV);
+        assert_eq!(
+            reflection.bindings,
+            vec![
+                binding(
+                    0,
+                    vk::DescriptorType::ACCELERATION_STRUCTURE_NV,
+                    1,
+                    vk::ShaderStageFlags::RAYGEN_NV
+                ),
+                binding(
+                    1,
+                    vk::DescriptorType::STORAGE_IMAGE,
+                    1,
+                    vk::ShaderStageFlags::RAYGEN_NV
+                ),
+                binding(
+                    3,
+                    vk::DescriptorType::UNIFORM_BUFFER,
+                    1,
+                    vk::ShaderStageFlags::RAYGEN_NV
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn bindless_closest_hit_declares_runtime_array() {
+        for name in &[
+            "triangle.hlsl_bindless_rchit.spv",
+            "triangle.glsl_bindless_rchit.spv",
+        ] {
+            let reflection = reflect_compiled(name);
+            assert_eq!(
+                reflection.bindings,
+                vec![binding(
+                    2,
+                    vk::DescriptorType::UNIFORM_BUFFER,
+                    0,
+                    vk::ShaderStageFlags::CLOSEST_HIT_NV
+                )]
+            );
+            assert!(reflection.bindings[0].is_runtime_array());
+        }
+    }
+
+    #[test]
+    fn pipeline_stages_merge_into_one_layout() {
+        let reflections: Vec<_> = [
+            "triangle.hlsl_rgen.spv",
+            "triangle.hlsl_bindless_rchit.spv",
+            "triangle.hlsl_rmiss.spv",
+        ]
+        .iter()
+        .map(|name| reflect_compiled(name))
+        .collect();
+        assert!(reflections[2].bindings.is_empty());
+
+        let merged = merge_bindings(&reflections).unwrap();
+        let summary: Vec<_> = merged
+            .iter()
+            .map(|binding| (binding.binding, binding.descriptor_type, binding.stages))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    0,
+                    vk::DescriptorType::ACCELERATION_STRUCTURE_NV,
+                    vk::ShaderStageFlags::RAYGEN_NV
+                ),
+                (
+                    1,
+                    vk::DescriptorType::STORAGE_IMAGE,
+                    vk::ShaderStageFlags::RAYGEN_NV
+                ),
+                (
+                    2,
+                    vk::DescriptorType::UNIFORM_BUFFER,
+                    vk::ShaderStageFlags::CLOSEST_HIT_NV
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn library_bindings_cover_all_entry_points() {
+        let reflection = reflect_compiled("triangle.hlsl_lib.spv");
+        let stages = vk::ShaderStageFlags::RAYGEN_NV
+            | vk::ShaderStageFlags::MISS_NV
+            | vk::ShaderStageFlags::CLOSEST_HIT_NV;
+        assert_eq!(reflection.stages, stages);
+        assert!(reflection
+            .bindings
+            .iter()
+            .all(|binding| binding.stages == stages));
+    }
+
+    #[test]
+    fn conflicting_declarations_are_rejected() {
+        let mut other = reflect_compiled("triangle.hlsl_rgen.spv");
+        other.bindings[1].descriptor_type = vk::DescriptorType::SAMPLED_IMAGE;
+        let reflections = [reflect_compiled("triangle.hlsl_rgen.spv"), other];
+        assert!(merge_bindings(&reflections).is_err());
+    }
+
+    #[test]
+    fn garbage_is_not_spirv() {
+        assert!(reflect(&[0, 1, 2, 3, 4]).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Buffers and images bound to memory from the base's allocator.
+
+use crate::allocator::{Allocation, AllocationKind};
+use crate::base::Base;
+use crate::error::Result;
+use ash::util::*;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::default::Default;
+use std::mem::align_of;
+use std::ops::Drop;
+use std::rc::Rc;
+
+/// An image with its backing memory and an optional view and sampler.
+pub struct ImageResource {
+    pub image: vk::Image,
+    pub allocation: Allocation,
+    pub view: vk::ImageView,
+    pub sampler: vk::Sampler,
+    base: Rc<Base>,
+}
+
+impl ImageResource {
+    /// Creates an empty resource; call `create_image` and `create_view` to
+    /// populate it.
+    pub fn new(base: Rc<Base>) -> Self {
+        ImageResource {
+            image: vk::Image::null(),
+            allocation: Allocation::default(),
+            view: vk::ImageView::null(),
+            sampler: vk::Sampler::null(),
+            base,
+        }
+    }
+
+    pub fn create_image(
+        &mut self,
+        image_type: vk::ImageType,
+        format: vk::Format,
+        extent: vk::Extent3D,
+        tiling: vk::ImageTiling,
+        usage: vk::ImageUsageFlags,
+        memory_flags: vk::MemoryPropertyFlags,
+    ) -> Result<()> {
+        unsafe {
+            let create_info = vk::ImageCreateInfo::builder()
+                .image_type(image_type)
+                .format(format)
+                .extent(extent)
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(tiling)
+                .usage(usage)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .build();
+
+            self.image = self.base.device.create_image(&create_info, None)?;
+
+            let requirements = self.base.device.get_image_memory_requirements(self.image);
+            self.allocation = self.base.allocator.allocate(
+                &self.base.device,
+                &requirements,
+                memory_flags,
+                AllocationKind::of_tiling(tiling),
+            )?;
+
+            self.base.device.bind_image_memory(
+                self.image,
+                self.allocation.memory,
+                self.allocation.offset,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn create_view(
+        &mut self,
+        view_type: vk::ImageViewType,
+        format: vk::Format,
+        range: vk::ImageSubresourceRange,
+    ) -> Result<()> {
+        let create_info = vk::ImageViewCreateInfo::builder()
+            .view_type(view_type)
+            .format(format)
+            .subresource_range(range)
+            .image(self.image)
+            .components(vk::ComponentMapping {
+                r: vk::ComponentSwizzle::R,
+                g: vk::ComponentSwizzle::G,
+                b: vk::ComponentSwizzle::B,
+                a: vk::ComponentSwizzle::A,
+            })
+            .build();
+        self.view = unsafe { self.base.device.create_image_view(&create_info, None)? };
+        Ok(())
+    }
+}
+
+impl Drop for ImageResource {
+    fn drop(&mut self) {
+        unsafe {
+            self.base.device.destroy_image_view(self.view, None);
+            self.base.device.destroy_image(self.image, None);
+            self.base
+                .allocator
+                .free(&self.base.device, &self.allocation);
+            self.base.device.destroy_sampler(self.sampler, None);
+        }
+    }
+}
+
+/// A buffer bound to a range of an allocator block.
+pub struct BufferResource {
+    pub buffer: vk::Buffer,
+    pub allocation: Allocation,
+    pub size: vk::DeviceSize,
+    base: Rc<Base>,
+}
+
+impl BufferResource {
+    pub fn new(
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        memory_properties: vk::MemoryPropertyFlags,
+        base: Rc<Base>,
+    ) -> Result<Self> {
+        unsafe {
+            let buffer_info = vk::BufferCreateInfo::builder()
+                .size(size)
+                .usage(usage)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .build();
+
+            let buffer = base.device.create_buffer(&buffer_info, None)?;
+
+            // Owning the handles right away lets Drop clean up on failure below.
+            let mut resource = BufferResource {
+                buffer,
+                allocation: Allocation::default(),
+                size,
+                base,
+            };
+
+            let memory_req = resource.base.device.get_buffer_memory_requirements(buffer);
+
+            resource.allocation = resource.base.allocator.allocate(
+                &resource.base.device,
+                &memory_req,
+                memory_properties,
+                AllocationKind::Linear,
+            )?;
+
+            resource.base.device.bind_buffer_memory(
+                buffer,
+                resource.allocation.memory,
+                resource.allocation.offset,
+            )?;
+
+            Ok(resource)
+        }
+    }
+
+    /// Copies `data` to the start of the buffer. The memory must be host
+    /// visible.
+    pub fn store<T: Copy>(&mut self, data: &[T]) -> Result<()> {
+        unsafe {
+            let size = std::mem::size_of_val(data) as u64;
+            let mapped_ptr = self.map(size)?;
+            let mut mapped_slice = Align::new(mapped_ptr, align_of::<T>() as u64, size);
+            mapped_slice.copy_from_slice(data);
+            self.unmap();
+        }
+        Ok(())
+    }
+
+    /// Maps the buffer's memory, of which the first `size` bytes may be
+    /// accessed. Other buffers in the same block may be mapped at once.
+    pub fn map(&mut self, size: vk::DeviceSize) -> Result<*mut std::ffi::c_void> {
+        debug_assert!(size <= self.allocation.size);
+        self.base.allocator.map(&self.base.device, &self.allocation)
+    }
+
+    pub fn unmap(&mut self) {
+        self.base
+            .allocator
+            .unmap(&self.base.device, &self.allocation);
+    }
+
+    /// Copies the whole buffer back to the host. The memory must be host
+    /// visible.
+    pub fn load(&mut self) -> Result<Vec<u8>> {
+        unsafe {
+            let mapped_ptr = self.map(self.size)?;
+            let data =
+                std::slice::from_raw_parts(mapped_ptr as *const u8, self.size as usize).to_vec();
+            self.unmap();
+            Ok(data)
+        }
+    }
+}
+
+impl Drop for BufferResource {
+    fn drop(&mut self) {
+        unsafe {
+            self.base.device.destroy_buffer(self.buffer, None);
+            self.base
+                .allocator
+                .free(&self.base.device, &self.allocation);
+        }
+    }
+}
+
+/// Picks a memory type allowed by `memory_req` with `flags`, preferring an
+/// exact match over a superset.
+pub fn find_memorytype_index(
+    memory_req: &vk::MemoryRequirements,
+    memory_prop: &vk::PhysicalDeviceMemoryProperties,
+    flags: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+    // Try to find an exactly matching memory flag
+    let best_suitable_index =
+        find_memorytype_index_f(memory_req, memory_prop, flags, |property_flags, flags| {
+            property_flags == flags
+        });
+    if best_suitable_index.is_some() {
+        return best_suitable_index;
+    }
+    // Otherwise find a memory flag that works
+    find_memorytype_index_f(memory_req, memory_prop, flags, |property_flags, flags| {
+        property_flags & flags == flags
+    })
+}
+
+pub fn find_memorytype_index_f<F: Fn(vk::MemoryPropertyFlags, vk::MemoryPropertyFlags) -> bool>(
+    memory_req: &vk::MemoryRequirements,
+    memory_prop: &vk::PhysicalDeviceMemoryProperties,
+    flags: vk::MemoryPropertyFlags,
+    f: F,
+) -> Option<u32> {
+    let mut memory_type_bits = memory_req.memory_type_bits;
+    for (index, memory_type) in memory_prop.memory_types.iter().enumerate() {
+        if memory_type_bits & 1 == 1 && f(memory_type.property_flags, flags) {
+            return Some(index as u32);
+        }
+        memory_type_bits >>= 1;
+    }
+    None
+}
@@ -0,0 +1,409 @@
+//! Scenes made of bottom-level models and their instances, built by hand or
+//! imported from glTF 2.0.
+
+use crate::acceleration_structure::{Aabb, Vertex};
+use crate::error::Result;
+use crate::mesh::Mesh;
+use std::path::Path;
+
+/// 3x4 row-major identity, in the layout of `GeometryInstance::transform`.
+pub const IDENTITY_TRANSFORM: [f32; 12] =
+    [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+/// Meshes built together into one bottom-level acceleration structure, one
+/// geometry per mesh. A model with `aabbs` is procedural instead: its
+/// primitives are found by an intersection shader, and `meshes` is ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Model {
+    pub name: String,
+    pub meshes: Vec<Mesh>,
+    /// Bounds of procedural primitives, built as one AABB geometry.
+    pub aabbs: Vec<Aabb>,
+    /// Vertices are rewritten every frame by a deformation shader and the
+    /// bottom level refitted, see `RayTracingApp::set_deformer`.
+    pub deformable: bool,
+}
+
+impl Model {
+    pub fn is_procedural(&self) -> bool {
+        !self.aabbs.is_empty()
+    }
+}
+
+/// One placement of a model in the top-level acceleration structure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshInstance {
+    /// Index into `Scene::models`.
+    pub model: usize,
+    /// 3x4 row-major object-to-world transform.
+    pub transform: [f32; 12],
+    /// Reported to shaders as the instance custom index; selects the color
+    /// in `Scene::materials`.
+    pub custom_id: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene {
+    pub models: Vec<Model>,
+    pub instances: Vec<MeshInstance>,
+    /// Linear RGB base color per material.
+    pub materials: Vec<[f32; 3]>,
+}
+
+impl Scene {
+    /// Three triangles in red, green and blue.
+    pub fn triangles() -> Self {
+        let translated = |x: f32, y: f32| {
+            let mut transform = IDENTITY_TRANSFORM;
+            transform[3] = x;
+            transform[7] = y;
+            transform
+        };
+        Scene {
+            models: vec![Model {
+                name: "triangle".to_owned(),
+                meshes: vec![Mesh::triangle()],
+                aabbs: Vec::new(),
+                deformable: false,
+            }],
+            instances: vec![
+                MeshInstance {
+                    model: 0,
+                    transform: translated(-1.5, 1.1),
+                    custom_id: 0,
+                },
+                MeshInstance {
+                    model: 0,
+                    transform: translated(0.0, -1.1),
+                    custom_id: 1,
+                },
+                MeshInstance {
+                    model: 0,
+                    transform: translated(1.5, 1.1),
+                    custom_id: 2,
+                },
+            ],
+            materials: vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        }
+    }
+
+    /// Adds a sphere of `radius` at `center`, for the sphere intersection
+    /// shader: a procedural model bounding the unit sphere, placed by the
+    /// instance transform. Returns the new model's index.
+    pub fn add_sphere(&mut self, center: [f32; 3], radius: f32, custom_id: u32) -> usize {
+        self.models.push(Model {
+            name: "sphere".to_owned(),
+            meshes: Vec::new(),
+            aabbs: vec![Aabb {
+                min: [-1.0, -1.0, -1.0],
+                max: [1.0, 1.0, 1.0],
+            }],
+            deformable: false,
+        });
+        let model = self.models.len() - 1;
+        self.instances.push(MeshInstance {
+            model,
+            transform: [
+                radius, 0.0, 0.0, center[0], 0.0, radius, 0.0, center[1], 0.0, 0.0, radius,
+                center[2],
+            ],
+            custom_id,
+        });
+        model
+    }
+
+    /// A single untransformed white instance of `meshes`, e.g. from
+    /// `load_obj`.
+    pub fn from_meshes(name: &str, meshes: Vec<Mesh>) -> Self {
+        Scene {
+            models: vec![Model {
+                name: name.to_owned(),
+                meshes,
+                aabbs: Vec::new(),
+                deformable: false,
+            }],
+            instances: vec![MeshInstance {
+                model: 0,
+                transform: IDENTITY_TRANSFORM,
+                custom_id: 0,
+            }],
+            materials: vec![[1.0, 1.0, 1.0]],
+        }
+    }
+}
+
+/// Loads a `.gltf` or `.glb` file, see `parse_gltf`.
+pub fn load_gltf(path: &Path) -> Result<Scene> {
+    let data = std::fs::read(path)?;
+    parse_gltf(&data, path.parent())
+}
+
+/// Imports the default scene (or the first one) of a glTF document. Every
+/// glTF mesh with triangle primitives becomes a model, and every node that
+/// references one becomes an instance with the node's world transform.
+/// External buffers are resolved relative to `base`.
+///
+/// Custom IDs carry the material of the mesh's first imported primitive;
+/// meshes without a material share a white default appended to the
+/// materials. Indices past the end of a primitive's vertices are reported
+/// as a validation error.
+pub fn parse_gltf(data: &[u8], base: Option<&Path>) -> Result<Scene> {
+    let gltf::Gltf { document, blob } = gltf::Gltf::from_slice(data)?;
+    let buffers = gltf::import_buffers(&document, base, blob)?;
+
+    let mut materials: Vec<[f32; 3]> = document
+        .materials()
+        .map(|material| {
+            let color = material.pbr_metallic_roughness().base_color_factor();
+            [color[0], color[1], color[2]]
+        })
+        .collect();
+    let mut default_material = None;
+
+    let mut models = Vec::new();
+    // glTF mesh index to (model index, custom ID)
+    let mut mesh_models = vec![None; document.meshes().len()];
+    for mesh in document.meshes() {
+        let name = mesh
+            .name()
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("mesh{}", mesh.index()));
+        let mut meshes = Vec::new();
+        // Instances take the material of the first primitive imported
+        let mut first_material = None;
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let vertices: Vec<Vertex> = match reader.read_positions() {
+                Some(positions) => positions.map(|pos| Vertex { pos }).collect(),
+                None => continue,
+            };
+            let normals = reader
+                .read_normals()
+                .map(|normals| normals.collect())
+                .unwrap_or_default();
+            let uvs = reader
+                .read_tex_coords(0)
+                .map(|uvs| uvs.into_f32().collect())
+                .unwrap_or_default();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+            if indices.is_empty() {
+                continue;
+            }
+            if indices
+                .iter()
+                .any(|&index| index as usize >= vertices.len())
+            {
+                let path = gltf::json::Path::new()
+                    .field("meshes")
+                    .index(mesh.index())
+                    .field("primitives")
+                    .index(primitive.index())
+                    .field("indices");
+                return Err(gltf::Error::Validation(vec![(
+                    path,
+                    gltf::json::validation::Error::IndexOutOfBounds,
+                )])
+                .into());
+            }
+            first_material.get_or_insert(primitive.material().index());
+            meshes.push(Mesh::new(name.clone(), vertices, normals, uvs, indices));
+        }
+        let first_material = match first_material {
+            Some(material) => material,
+            None => continue,
+        };
+
+        let material = match first_material {
+            Some(index) => index,
+            None => *default_material.get_or_insert_with(|| {
+                materials.push([1.0, 1.0, 1.0]);
+                materials.len() - 1
+            }),
+        };
+        mesh_models[mesh.index()] = Some((models.len(), material as u32));
+        models.push(Model {
+            name,
+            meshes,
+            aabbs: Vec::new(),
+            deformable: false,
+        });
+    }
+
+    let mut instances = Vec::new();
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in scene.nodes() {
+            collect_instances(&node, IDENTITY_MATRIX, &mesh_models, &mut instances);
+        }
+    }
+
+    Ok(Scene {
+        models,
+        instances,
+        materials,
+    })
+}
+
+/// Column-major 4x4, as glTF stores node matrices.
+type Matrix = [[f32; 4]; 4];
+
+const IDENTITY_MATRIX: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn collect_instances(
+    node: &gltf::Node,
+    parent: Matrix,
+    mesh_models: &[Option<(usize, u32)>],
+    instances: &mut Vec<MeshInstance>,
+) {
+    let world = multiply(&parent, &node.transform().matrix());
+    if let Some((model, custom_id)) = node.mesh().and_then(|mesh| mesh_models[mesh.index()]) {
+        instances.push(MeshInstance {
+            model,
+            transform: to_row_major_3x4(&world),
+            custom_id,
+        });
+    }
+    for child in node.children() {
+        collect_instances(&child, world, mesh_models, instances);
+    }
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0.0; 4]; 4];
+    for (column, result_column) in result.iter_mut().enumerate() {
+        for (row, value) in result_column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    result
+}
+
+fn to_row_major_3x4(matrix: &Matrix) -> [f32; 12] {
+    let mut transform = [0.0; 12];
+    for row in 0..3 {
+        for column in 0..4 {
+            transform[row * 4 + column] = matrix[column][row];
+        }
+    }
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    const NESTED_TRIANGLE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "translation": [1, 2, 3], "children": [1] },
+            { "mesh": 0, "matrix": [0, 1, 0, 0, -1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1] }
+        ],
+        "meshes": [{
+            "name": "triangle",
+            "primitives": [{ "attributes": { "POSITION": 0 }, "material": 1 }]
+        }],
+        "materials": [
+            {},
+            { "pbrMetallicRoughness": { "baseColorFactor": [0.5, 0.25, 1, 1] } }
+        ],
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0, 0, 0], "max": [1, 1, 0]
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }]
+    }"#;
+
+    #[test]
+    fn node_transforms_are_composed_row_major() {
+        let scene = parse_gltf(NESTED_TRIANGLE.as_bytes(), None).unwrap();
+        assert_eq!(scene.models.len(), 1);
+        assert_eq!(scene.models[0].meshes[0].indices.len(), 3);
+        assert_eq!(
+            scene.instances,
+            vec![MeshInstance {
+                model: 0,
+                transform: [0.0, -1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 1.0, 3.0],
+                custom_id: 1,
+            }]
+        );
+        assert_eq!(scene.materials[1], [0.5, 0.25, 1.0]);
+    }
+
+    /// A triangle with indices `[0, 1, last]`, drawn after a point
+    /// primitive that uses the other material.
+    fn indexed_triangle(last: u16) -> String {
+        let uri = if last == 2 {
+            "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA="
+        } else {
+            assert_eq!(last, 3);
+            "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAMAAAA="
+        };
+        format!(
+            r#"{{
+            "asset": {{ "version": "2.0" }},
+            "scenes": [{{ "nodes": [0] }}],
+            "nodes": [{{ "mesh": 0 }}],
+            "meshes": [{{
+                "primitives": [
+                    {{ "attributes": {{ "POSITION": 0 }}, "mode": 0, "material": 0 }},
+                    {{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 1 }}
+                ]
+            }}],
+            "materials": [{{}}, {{}}],
+            "accessors": [
+                {{
+                    "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0, 0, 0], "max": [1, 1, 0]
+                }},
+                {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+            ],
+            "bufferViews": [
+                {{ "buffer": 0, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+            ],
+            "buffers": [{{
+                "byteLength": 44,
+                "uri": "data:application/octet-stream;base64,{}"
+            }}]
+        }}"#,
+            uri
+        )
+    }
+
+    #[test]
+    fn custom_id_is_the_first_imported_primitive_material() {
+        let scene = parse_gltf(indexed_triangle(2).as_bytes(), None).unwrap();
+        assert_eq!(scene.models[0].meshes.len(), 1);
+        assert_eq!(scene.instances[0].custom_id, 1);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        match parse_gltf(indexed_triangle(3).as_bytes(), None) {
+            Err(Error::Gltf(gltf::Error::Validation(errors))) => {
+                assert_eq!(errors[0].0.as_str(), "meshes[0].primitives[1].indices");
+            }
+            other => panic!("expected a validation error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
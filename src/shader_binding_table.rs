@@ -0,0 +1,358 @@
+//! Shader binding table storage for `cmd_trace_rays`.
+
+use crate::base::Base;
+use crate::error::{Error, Result};
+use crate::resource::BufferResource;
+use ash::extensions::nv;
+use ash::vk;
+use std::rc::Rc;
+
+/// One entry of the table: the handle of a pipeline shader group, followed
+/// by inline data the shaders read through `shaderRecordNV` (GLSL) or
+/// `[[vk::shader_record_nv]]` (HLSL).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShaderRecord {
+    /// Index of the group in `vk::RayTracingPipelineCreateInfoNV::groups`.
+    pub group: u32,
+    pub data: Vec<u8>,
+}
+
+impl ShaderRecord {
+    pub fn new(group: u32) -> Self {
+        ShaderRecord {
+            group,
+            data: Vec::new(),
+        }
+    }
+
+    /// A record with the bytes of `data`, e.g. a `#[repr(C)]` struct of
+    /// material constants, after the handle.
+    pub fn with_data<T: Copy>(group: u32, data: &T) -> Self {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(data as *const T as *const u8, std::mem::size_of::<T>())
+        };
+        ShaderRecord {
+            group,
+            data: bytes.to_vec(),
+        }
+    }
+}
+
+/// Where one kind of record lives in the table.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ShaderBindingRegion {
+    /// Byte offset of the first record, a multiple of
+    /// `shader_group_base_alignment`.
+    pub offset: vk::DeviceSize,
+    /// Bytes from one record to the next.
+    pub stride: vk::DeviceSize,
+    pub count: u32,
+}
+
+impl ShaderBindingRegion {
+    /// Byte offset of record `index`.
+    pub fn record_offset(&self, index: u32) -> vk::DeviceSize {
+        self.offset + vk::DeviceSize::from(index) * self.stride
+    }
+}
+
+/// Region placement of a shader binding table, computed on the host from
+/// record sizes and device limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ShaderBindingTableLayout {
+    pub raygen: ShaderBindingRegion,
+    pub miss: ShaderBindingRegion,
+    pub hit: ShaderBindingRegion,
+    pub callable: ShaderBindingRegion,
+    /// Total size of the table in bytes.
+    pub size: vk::DeviceSize,
+}
+
+/// Collects raygen, miss, hit and callable records, then lays them out and
+/// uploads them in that order.
+///
+/// Each region starts at a multiple of `shader_group_base_alignment`. Its
+/// stride is the largest record rounded up to the handle size; raygen
+/// records are rounded up to the base alignment too, since `cmd_trace_rays`
+/// takes the offset of a single raygen record.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderBindingTableBuilder {
+    raygen: Vec<ShaderRecord>,
+    miss: Vec<ShaderRecord>,
+    hit: Vec<ShaderRecord>,
+    callable: Vec<ShaderRecord>,
+}
+
+impl ShaderBindingTableBuilder {
+    pub fn new() -> Self {
+        ShaderBindingTableBuilder::default()
+    }
+
+    /// Adds a raygen record and returns its index in the raygen region.
+    pub fn add_raygen(&mut self, record: ShaderRecord) -> u32 {
+        push_record(&mut self.raygen, record)
+    }
+
+    /// Adds a miss record and returns its index, the miss index passed to
+    /// `TraceRay`.
+    pub fn add_miss(&mut self, record: ShaderRecord) -> u32 {
+        push_record(&mut self.miss, record)
+    }
+
+    /// Adds a hit group record and returns its index, which instances select
+    /// through their hit group offset.
+    pub fn add_hit(&mut self, record: ShaderRecord) -> u32 {
+        push_record(&mut self.hit, record)
+    }
+
+    /// Adds a callable record and returns its index, the one passed to
+    /// `CallShader`.
+    pub fn add_callable(&mut self, record: ShaderRecord) -> u32 {
+        push_record(&mut self.callable, record)
+    }
+
+    /// Places the regions for a device with the given limits.
+    pub fn layout(
+        &self,
+        handle_size: u32,
+        base_alignment: u32,
+        max_stride: u32,
+    ) -> Result<ShaderBindingTableLayout> {
+        let handle_size = vk::DeviceSize::from(handle_size);
+        let base_alignment = vk::DeviceSize::from(base_alignment);
+
+        let mut size = 0;
+        let mut region = |records: &[ShaderRecord], record_alignment| {
+            let largest = records
+                .iter()
+                .map(|record| handle_size + record.data.len() as vk::DeviceSize)
+                .max()
+                .unwrap_or(0);
+            let stride = align_up(largest, record_alignment);
+            if stride > vk::DeviceSize::from(max_stride) {
+                return Err(Error::ShaderRecordTooLarge {
+                    stride,
+                    max: max_stride,
+                });
+            }
+            let offset = align_up(size, base_alignment);
+            size = offset + stride * records.len() as vk::DeviceSize;
+            Ok(ShaderBindingRegion {
+                offset,
+                stride,
+                count: records.len() as u32,
+            })
+        };
+
+        let raygen = region(&self.raygen, align_up(handle_size, base_alignment))?;
+        let miss = region(&self.miss, handle_size)?;
+        let hit = region(&self.hit, handle_size)?;
+        let callable = region(&self.callable, handle_size)?;
+
+        Ok(ShaderBindingTableLayout {
+            raygen,
+            miss,
+            hit,
+            callable,
+            size,
+        })
+    }
+
+    /// Number of pipeline groups whose handles `pack` needs.
+    pub fn group_count(&self) -> u32 {
+        self.records()
+            .map(|record| record.group + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Writes every record into table memory following `layout`. `handles`
+    /// holds the handles of groups `0..group_count()`, `handle_size` bytes
+    /// each, as returned by `get_ray_tracing_shader_group_handles`.
+    pub fn pack(
+        &self,
+        layout: &ShaderBindingTableLayout,
+        handles: &[u8],
+        handle_size: u32,
+    ) -> Vec<u8> {
+        let handle_size = handle_size as usize;
+        let mut table = vec![0u8; layout.size as usize];
+        for (region, records) in [
+            (&layout.raygen, &self.raygen),
+            (&layout.miss, &self.miss),
+            (&layout.hit, &self.hit),
+            (&layout.callable, &self.callable),
+        ] {
+            for (index, record) in records.iter().enumerate() {
+                let start = region.record_offset(index as u32) as usize;
+                let handle_start = record.group as usize * handle_size;
+                table[start..start + handle_size]
+                    .copy_from_slice(&handles[handle_start..handle_start + handle_size]);
+                let data_start = start + handle_size;
+                table[data_start..data_start + record.data.len()].copy_from_slice(&record.data);
+            }
+        }
+        table
+    }
+
+    /// Fetches the group handles of `pipeline` and uploads the table.
+    pub fn build(
+        &self,
+        base: Rc<Base>,
+        ray_tracing: &nv::RayTracing,
+        properties: &vk::PhysicalDeviceRayTracingPropertiesNV,
+        pipeline: vk::Pipeline,
+    ) -> Result<ShaderBindingTable> {
+        let handle_size = properties.shader_group_handle_size;
+        let layout = self.layout(
+            handle_size,
+            properties.shader_group_base_alignment,
+            properties.max_shader_group_stride,
+        )?;
+
+        let group_count = self.group_count();
+        let mut handles = vec![0u8; (handle_size * group_count) as usize];
+        if group_count > 0 {
+            unsafe {
+                ray_tracing.get_ray_tracing_shader_group_handles(
+                    pipeline,
+                    0,
+                    group_count,
+                    &mut handles,
+                )?;
+            }
+        }
+        let table_data = self.pack(&layout, &handles, handle_size);
+
+        let mut buffer = BufferResource::new(
+            layout.size.max(1),
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+            base,
+        )?;
+        buffer.store(&table_data)?;
+        Ok(ShaderBindingTable { buffer, layout })
+    }
+
+    fn records(&self) -> impl Iterator<Item = &ShaderRecord> {
+        self.raygen
+            .iter()
+            .chain(&self.miss)
+            .chain(&self.hit)
+            .chain(&self.callable)
+    }
+}
+
+/// Shader records of a ray tracing pipeline in a host visible buffer.
+pub struct ShaderBindingTable {
+    pub buffer: BufferResource,
+    pub layout: ShaderBindingTableLayout,
+}
+
+impl ShaderBindingTable {
+    /// The table buffer if `region` holds any records, else a null handle
+    /// as `cmd_trace_rays` expects for unused regions.
+    pub fn region_buffer(&self, region: &ShaderBindingRegion) -> vk::Buffer {
+        if region.count > 0 {
+            self.buffer.buffer
+        } else {
+            vk::Buffer::null()
+        }
+    }
+}
+
+fn push_record(records: &mut Vec<ShaderRecord>, record: ShaderRecord) -> u32 {
+    records.push(record);
+    records.len() as u32 - 1
+}
+
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    if alignment == 0 {
+        value
+    } else {
+        value.div_ceil(alignment) * alignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HANDLE_SIZE: u32 = 16;
+    const BASE_ALIGNMENT: u32 = 64;
+    const MAX_STRIDE: u32 = 4096;
+
+    fn triangle_builder() -> ShaderBindingTableBuilder {
+        let mut builder = ShaderBindingTableBuilder::new();
+        builder.add_raygen(ShaderRecord::new(0));
+        builder.add_hit(ShaderRecord::new(1));
+        builder.add_miss(ShaderRecord::new(2));
+        builder
+    }
+
+    #[test]
+    fn regions_start_at_base_alignment() {
+        let layout = triangle_builder()
+            .layout(HANDLE_SIZE, BASE_ALIGNMENT, MAX_STRIDE)
+            .unwrap();
+        let region = |offset, stride, count| ShaderBindingRegion {
+            offset,
+            stride,
+            count,
+        };
+        assert_eq!(layout.raygen, region(0, 64, 1));
+        assert_eq!(layout.miss, region(64, 16, 1));
+        assert_eq!(layout.hit, region(128, 16, 1));
+        assert_eq!(layout.callable, region(192, 0, 0));
+        assert_eq!(layout.size, 192);
+    }
+
+    #[test]
+    fn inline_data_widens_the_stride_of_its_region() {
+        let mut builder = triangle_builder();
+        builder.add_hit(ShaderRecord::with_data(1, &[0.5f32, 0.25, 1.0]));
+        builder.add_callable(ShaderRecord::new(3));
+        let layout = builder
+            .layout(HANDLE_SIZE, BASE_ALIGNMENT, MAX_STRIDE)
+            .unwrap();
+        assert_eq!(layout.hit.stride, 32);
+        assert_eq!(layout.hit.count, 2);
+        assert_eq!(layout.hit.record_offset(1), 160);
+        assert_eq!(layout.callable.offset, 192);
+        assert_eq!(layout.size, 208);
+        assert_eq!(builder.group_count(), 4);
+    }
+
+    #[test]
+    fn records_over_the_maximum_stride_are_rejected() {
+        let mut builder = triangle_builder();
+        builder.add_miss(ShaderRecord {
+            group: 2,
+            data: vec![0; 100],
+        });
+        match builder.layout(HANDLE_SIZE, BASE_ALIGNMENT, 64) {
+            Err(Error::ShaderRecordTooLarge { stride, max }) => {
+                assert_eq!((stride, max), (128, 64));
+            }
+            other => panic!("Unexpected layout {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pack_places_handles_and_data() {
+        let mut builder = triangle_builder();
+        builder.add_hit(ShaderRecord {
+            group: 1,
+            data: vec![0xaa; 4],
+        });
+        let layout = builder.layout(4, 8, MAX_STRIDE).unwrap();
+        let handles: Vec<u8> = (0..12).collect();
+        let table = builder.pack(&layout, &handles, 4);
+
+        assert_eq!(&table[0..4], &[0, 1, 2, 3]);
+        assert_eq!(&table[8..12], &[8, 9, 10, 11]);
+        assert_eq!(&table[16..20], &[4, 5, 6, 7]);
+        assert_eq!(&table[24..32], &[4, 5, 6, 7, 0xaa, 0xaa, 0xaa, 0xaa]);
+        assert_eq!(table.len(), 32);
+    }
+}
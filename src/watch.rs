@@ -0,0 +1,99 @@
+//! Polling watcher that notices edited shaders, for hot reloading.
+
+use crate::error::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Remembers the modification times of the shader sources (`.hlsl`,
+/// `.glsl`) and SPIR-V binaries (`.spv`) below a directory, such as
+/// `shaders/` with its `compiled/` subdirectory.
+pub struct ShaderWatcher {
+    root: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderWatcher {
+    /// Starts watching `root`. Files already there are not reported as
+    /// changed.
+    pub fn new(root: &Path) -> Result<Self> {
+        let mut watcher = ShaderWatcher {
+            root: root.to_owned(),
+            modified: HashMap::new(),
+        };
+        watcher.modified = watcher.scan()?;
+        Ok(watcher)
+    }
+
+    /// Shaders written or created since the last call, sorted by path.
+    pub fn changed(&mut self) -> Result<Vec<PathBuf>> {
+        let modified = self.scan()?;
+        let mut changed: Vec<PathBuf> = modified
+            .iter()
+            .filter(|&(path, time)| self.modified.get(path) != Some(time))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.sort();
+        self.modified = modified;
+        Ok(changed)
+    }
+
+    fn scan(&self) -> Result<HashMap<PathBuf, SystemTime>> {
+        let mut modified = HashMap::new();
+        let mut directories = vec![self.root.clone()];
+        while let Some(directory) = directories.pop() {
+            for entry in fs::read_dir(&directory)? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                let path = entry.path();
+                if metadata.is_dir() {
+                    directories.push(path);
+                } else if is_shader(&path) {
+                    modified.insert(path, metadata.modified()?);
+                }
+            }
+        }
+        Ok(modified)
+    }
+}
+
+fn is_shader(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("hlsl") | Some("glsl") | Some("spv")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    #[test]
+    fn reports_written_and_new_shaders_once() {
+        let root = std::env::temp_dir().join(format!("ash-nv-rt-watch-{}", std::process::id()));
+        fs::create_dir_all(root.join("compiled")).unwrap();
+        let source = root.join("triangle.rchit.hlsl");
+        fs::write(&source, "void main() {}").unwrap();
+        fs::write(root.join("notes.txt"), "").unwrap();
+
+        let mut watcher = ShaderWatcher::new(&root).unwrap();
+        assert!(watcher.changed().unwrap().is_empty());
+
+        let compiled = root.join("compiled/triangle.hlsl_rchit.spv");
+        fs::write(&compiled, [0u8; 4]).unwrap();
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(watcher.changed().unwrap(), vec![compiled, source]);
+        assert!(watcher.changed().unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}